@@ -0,0 +1,79 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rs4neo::bolt::message::{pack, unpack, MessageStructure, MessageValue, PackStream};
+
+// A RECORD carrying a node-like structure with a handful of mixed fields.
+fn record() -> (MessageValue, Vec<u8>) {
+    let node = MessageStructure::new(
+        0x4E,
+        vec![
+            MessageValue::SmallInt(1234),
+            MessageValue::String("Alice".to_string()),
+            MessageValue::String("alice@example.com/people".to_string()),
+            MessageValue::Float(1.75),
+            MessageValue::BigInt(0x0000_017F_0000_0000),
+        ],
+    );
+    let record = MessageStructure::new(0x71, vec![MessageValue::Structure(node)]);
+    let value = MessageValue::Structure(record);
+    let encoded = pack(value.clone()).unwrap();
+    (value, encoded)
+}
+
+fn chunked(message: &[u8], count: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity((message.len() + 4) * count);
+    for _ in 0..count {
+        data.extend((message.len() as u16).to_be_bytes());
+        data.extend(message);
+        data.extend([0x00, 0x00]);
+    }
+    data
+}
+
+fn bench_pack(c: &mut Criterion) {
+    let (value, encoded) = record();
+    let mut group = c.benchmark_group("pack");
+    group.throughput(Throughput::Bytes(encoded.len() as u64));
+    group.bench_function("record", |b| {
+        b.iter_batched(
+            || value.clone(),
+            |value| pack(black_box(value)).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn bench_unpack(c: &mut Criterion) {
+    let (_, encoded) = record();
+    let mut group = c.benchmark_group("unpack");
+    group.throughput(Throughput::Bytes(encoded.len() as u64));
+    group.bench_function("record", |b| {
+        b.iter(|| unpack(black_box(&encoded)).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_read_message(c: &mut Criterion) {
+    const RECORDS: usize = 1000;
+    let (_, encoded) = record();
+    let data = chunked(&encoded, RECORDS);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("read_message");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("1000 records", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let mut stream = PackStream::new(black_box(&data[..]), tokio::io::sink());
+                for _ in 0..RECORDS {
+                    stream.read_message().await.unwrap();
+                }
+            })
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_pack, bench_unpack, bench_read_message);
+criterion_main!(benches);
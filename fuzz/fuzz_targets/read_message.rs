@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rs4neo::bolt::message::PackStream;
+
+// Feeds the input as the inbound side of a connection and reads messages until
+// the stream errors, exercising chunk reassembly as well as unpacking.
+fuzz_target!(|data: &[u8]| {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let mut stream = PackStream::new(data, tokio::io::sink());
+        while stream.read_message().await.is_ok() {}
+    });
+});
@@ -0,0 +1,216 @@
+use std::fmt;
+
+use crate::bolt::message::MessageValue;
+use crate::bolt::messages::Dictionary;
+
+#[derive(Clone, PartialEq)]
+pub enum Auth {
+    None,
+    Basic {
+        user: String,
+        password: String,
+        realm: Option<String>,
+    },
+    // An SSO access token.
+    Bearer {
+        token: String,
+    },
+    // A base64 encoded Kerberos ticket.
+    Kerberos {
+        ticket: String,
+    },
+    // Any scheme understood by a server-side auth plugin.
+    Custom {
+        scheme: String,
+        principal: String,
+        credentials: String,
+        realm: Option<String>,
+        parameters: Dictionary,
+    },
+}
+
+impl Auth {
+    pub fn basic(user: &str, password: &str) -> Auth {
+        Auth::Basic {
+            user: user.to_string(),
+            password: password.to_string(),
+            realm: None,
+        }
+    }
+
+    pub fn basic_with_realm(user: &str, password: &str, realm: &str) -> Auth {
+        Auth::Basic {
+            user: user.to_string(),
+            password: password.to_string(),
+            realm: Some(realm.to_string()),
+        }
+    }
+
+    pub fn bearer(token: &str) -> Auth {
+        Auth::Bearer {
+            token: token.to_string(),
+        }
+    }
+
+    pub fn kerberos(ticket: &str) -> Auth {
+        Auth::Kerberos {
+            ticket: ticket.to_string(),
+        }
+    }
+
+    pub fn custom(
+        scheme: &str,
+        principal: &str,
+        credentials: &str,
+        realm: Option<&str>,
+        parameters: Dictionary,
+    ) -> Auth {
+        Auth::Custom {
+            scheme: scheme.to_string(),
+            principal: principal.to_string(),
+            credentials: credentials.to_string(),
+            realm: realm.map(str::to_string),
+            parameters,
+        }
+    }
+
+    // The auth token sent in HELLO (before Bolt 5.1) or LOGON.
+    pub(crate) fn token(&self) -> Dictionary {
+        let mut token = Dictionary::new();
+        let mut put = |key: &str, value: &str| {
+            token.insert(key.to_string(), string(value));
+        };
+        match self {
+            Auth::None => put("scheme", "none"),
+            Auth::Basic {
+                user,
+                password,
+                realm,
+            } => {
+                put("scheme", "basic");
+                put("principal", user);
+                put("credentials", password);
+                if let Some(realm) = realm {
+                    put("realm", realm);
+                }
+            }
+            Auth::Bearer { token } => {
+                put("scheme", "bearer");
+                put("credentials", token);
+            }
+            // The principal is required by the server but not used.
+            Auth::Kerberos { ticket } => {
+                put("scheme", "kerberos");
+                put("principal", "");
+                put("credentials", ticket);
+            }
+            Auth::Custom {
+                scheme,
+                principal,
+                credentials,
+                realm,
+                parameters,
+            } => {
+                put("scheme", scheme);
+                put("principal", principal);
+                put("credentials", credentials);
+                if let Some(realm) = realm {
+                    put("realm", realm);
+                }
+                if !parameters.is_empty() {
+                    token.insert(
+                        "parameters".to_string(),
+                        MessageValue::Map(parameters.clone()),
+                    );
+                }
+            }
+        }
+        token
+    }
+}
+
+fn string(s: &str) -> MessageValue {
+    MessageValue::String(s.to_string())
+}
+
+// Credentials stay out of logs.
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::None => write!(f, "Auth::None"),
+            Auth::Basic { user, realm, .. } => {
+                write!(
+                    f,
+                    "Auth::Basic {{ user: {:?}, realm: {:?}, .. }}",
+                    user, realm
+                )
+            }
+            Auth::Bearer { .. } => write!(f, "Auth::Bearer {{ .. }}"),
+            Auth::Kerberos { .. } => write!(f, "Auth::Kerberos {{ .. }}"),
+            Auth::Custom {
+                scheme, principal, ..
+            } => write!(
+                f,
+                "Auth::Custom {{ scheme: {:?}, principal: {:?}, .. }}",
+                scheme, principal
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(auth: Auth) -> Vec<(String, MessageValue)> {
+        let mut token: Vec<_> = auth.token().into_iter().collect();
+        token.sort_by(|a, b| a.0.cmp(&b.0));
+        token
+    }
+
+    fn entries(entries: &[(&str, &str)]) -> Vec<(String, MessageValue)> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), string(v)))
+            .collect()
+    }
+
+    #[test]
+    fn builds_tokens_for_each_scheme() {
+        assert_eq!(token(Auth::None), entries(&[("scheme", "none")]));
+        assert_eq!(
+            token(Auth::basic_with_realm("neo4j", "secret", "native")),
+            entries(&[
+                ("credentials", "secret"),
+                ("principal", "neo4j"),
+                ("realm", "native"),
+                ("scheme", "basic"),
+            ])
+        );
+        assert_eq!(
+            token(Auth::bearer("jwt")),
+            entries(&[("credentials", "jwt"), ("scheme", "bearer")])
+        );
+        assert_eq!(
+            token(Auth::kerberos("dGlja2V0")),
+            entries(&[
+                ("credentials", "dGlja2V0"),
+                ("principal", ""),
+                ("scheme", "kerberos"),
+            ])
+        );
+
+        let parameters = Dictionary::from([("tenant".to_string(), string("acme"))]);
+        let custom = Auth::custom("plugin", "me", "key", None, parameters.clone()).token();
+        assert_eq!(custom["scheme"], string("plugin"));
+        assert_eq!(custom["parameters"], MessageValue::Map(parameters));
+        assert!(!custom.contains_key("realm"));
+    }
+
+    #[test]
+    fn debug_output_hides_credentials() {
+        let debug = format!("{:?}", Auth::basic("neo4j", "secret"));
+        assert!(debug.contains("neo4j") && !debug.contains("secret"));
+        assert!(!format!("{:?}", Auth::bearer("jwt")).contains("jwt"));
+    }
+}
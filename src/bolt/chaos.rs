@@ -0,0 +1,261 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Sleep;
+
+// Wraps a transport and injects the failures a real network produces: delayed
+// operations, short reads and writes, connection resets and connections that
+// drop partway through a chunk. Everything is driven from `seed` so a failing
+// run can be replayed exactly.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChaosConfig {
+    // Connections a pool opens are seeded with this plus their number.
+    pub seed: u64,
+    // Each read or write waits for a random delay of up to this long first.
+    pub max_latency: Option<Duration>,
+    // Split reads and writes into random pieces of at most this many bytes.
+    pub max_io_size: Option<usize>,
+    // Chance per read or write of the connection being reset.
+    pub disconnect_probability: f64,
+    // Close the connection once this many bytes have been read, truncating
+    // whatever frame was in flight.
+    pub truncate_after: Option<usize>,
+    // Only the first this many connections of a pool get faults, so that
+    // recovery onto a healthy one can be tested. None means all of them.
+    pub connections: Option<usize>,
+}
+
+pub struct ChaosStream<S> {
+    inner: S,
+    config: ChaosConfig,
+    rng: u64,
+    delay: Option<Pin<Box<Sleep>>>,
+    delayed: bool,
+    bytes_read: usize,
+    disconnected: bool,
+}
+
+impl<S> ChaosStream<S> {
+    pub fn new(inner: S, config: ChaosConfig) -> Self {
+        Self {
+            inner,
+            // xorshift gets stuck on zero
+            rng: config.seed | 1,
+            config,
+            delay: None,
+            delayed: false,
+            bytes_read: 0,
+            disconnected: false,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && (self.next_random() as f64 / u64::MAX as f64) < probability
+    }
+
+    // Picks how many of `available` bytes the next operation may move.
+    fn io_len(&mut self, available: usize) -> usize {
+        match self.config.max_io_size {
+            Some(max_io_size) if available > 1 => {
+                1 + (self.next_random() as usize % available.min(max_io_size.max(1)))
+            }
+            _ => available,
+        }
+    }
+
+    fn poll_latency(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let max_latency = match self.config.max_latency {
+            Some(max_latency) if !self.delayed => max_latency,
+            _ => return Poll::Ready(()),
+        };
+        if self.delay.is_none() {
+            let nanos = self.next_random() % (max_latency.as_nanos() as u64 + 1);
+            self.delay = Some(Box::pin(tokio::time::sleep(Duration::from_nanos(nanos))));
+        }
+        match self.delay.as_mut().unwrap().as_mut().poll(cx) {
+            Poll::Ready(()) => {
+                self.delay = None;
+                self.delayed = true;
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    // Runs the pre-operation faults, returning Some when the operation should
+    // fail or wait instead of reaching the inner transport.
+    fn poll_faults(&mut self, cx: &mut Context<'_>) -> Option<Poll<std::io::Result<()>>> {
+        if self.disconnected {
+            return Some(Poll::Ready(Err(reset())));
+        }
+        if self.poll_latency(cx).is_pending() {
+            return Some(Poll::Pending);
+        }
+        if self.chance(self.config.disconnect_probability) {
+            self.disconnected = true;
+            return Some(Poll::Ready(Err(reset())));
+        }
+        None
+    }
+}
+
+fn reset() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::ConnectionReset,
+        "chaos: connection reset",
+    )
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ChaosStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(fault) = this.poll_faults(cx) {
+            return fault;
+        }
+        let mut len = this.io_len(buf.remaining());
+        if let Some(limit) = this.config.truncate_after {
+            len = len.min(limit.saturating_sub(this.bytes_read));
+            if len == 0 {
+                return Poll::Ready(Ok(()));
+            }
+        }
+        let mut scratch = vec![0; len];
+        let mut limited = ReadBuf::new(&mut scratch);
+        match Pin::new(&mut this.inner).poll_read(cx, &mut limited) {
+            Poll::Ready(Ok(())) => {
+                this.delayed = false;
+                this.bytes_read += limited.filled().len();
+                buf.put_slice(limited.filled());
+                Poll::Ready(Ok(()))
+            }
+            other => other,
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ChaosStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        match this.poll_faults(cx) {
+            Some(Poll::Ready(Err(e))) => return Poll::Ready(Err(e)),
+            Some(_) => return Poll::Pending,
+            None => {}
+        }
+        let len = this.io_len(buf.len());
+        let result = Pin::new(&mut this.inner).poll_write(cx, &buf[..len]);
+        if result.is_ready() {
+            this.delayed = false;
+        }
+        result
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bolt::message::{unpack, MessageValue, PackStream};
+    use crate::error::Error;
+    use tokio::io::AsyncWriteExt;
+
+    // A RECORD split over two chunks.
+    fn record(i: u8) -> Vec<u8> {
+        let mut data = vec![0x00, 0x04, 0xB2, 0x71, i & 0x7F, 0x83];
+        data.extend([0x00, 0x03, b'x', b'y', b'z', 0x00, 0x00]);
+        data
+    }
+
+    fn expected(i: u8) -> MessageValue {
+        unpack(&[0xB2, 0x71, i & 0x7F, 0x83, b'x', b'y', b'z']).unwrap()
+    }
+
+    #[tokio::test]
+    async fn messages_survive_short_reads_latency_and_cancellation() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let config = ChaosConfig {
+            seed: 7,
+            max_latency: Some(Duration::from_millis(2)),
+            max_io_size: Some(5),
+            ..ChaosConfig::default()
+        };
+        let (reader, writer) = tokio::io::split(ChaosStream::new(client, config));
+        let mut stream = PackStream::new(reader, writer);
+        tokio::spawn(async move {
+            for i in 0..50 {
+                server.write_all(&record(i)).await.unwrap();
+            }
+        });
+
+        for i in 0..50 {
+            let message = loop {
+                let read = stream.read_message();
+                if let Ok(message) = tokio::time::timeout(Duration::from_millis(1), read).await {
+                    break message.unwrap();
+                }
+            };
+            assert!(message == expected(i), "message {} was corrupted", i);
+        }
+    }
+
+    #[tokio::test]
+    async fn truncated_connection_is_an_error() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let config = ChaosConfig {
+            truncate_after: Some(record(0).len() + 5),
+            ..ChaosConfig::default()
+        };
+        let (reader, writer) = tokio::io::split(ChaosStream::new(client, config));
+        let mut stream = PackStream::new(reader, writer);
+        server.write_all(&record(0)).await.unwrap();
+        server.write_all(&record(1)).await.unwrap();
+
+        assert!(stream.read_message().await.unwrap() == expected(0));
+        match stream.read_message().await.err().unwrap() {
+            Error::Connection(e) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn reset_connections_stay_reset() {
+        let (client, _server) = tokio::io::duplex(1024);
+        let config = ChaosConfig {
+            disconnect_probability: 1.0,
+            ..ChaosConfig::default()
+        };
+        let mut chaos = ChaosStream::new(client, config);
+        let err = chaos.write_all(b"hello").await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset);
+        let err = chaos.write_all(b"hello").await.err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset);
+    }
+}
@@ -0,0 +1,344 @@
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+
+#[cfg(any(test, feature = "chaos"))]
+use super::chaos::ChaosStream;
+use super::handshake::{handshake, BoltVersion, DEFAULT_PROPOSALS};
+use super::message::{MessageStructure, MessageValue, PackStream};
+use super::messages::{
+    Dictionary, Discard, Failure, Hello, Logon, Pull, Reset, Response, Rollback, Success, BEGIN,
+    COMMIT, DISCARD, PULL, ROLLBACK,
+};
+use super::tls;
+use crate::auth::Auth;
+use crate::config::Config;
+use crate::error::Error;
+use crate::routing::routing_context;
+
+pub type BoxedReader = Box<dyn AsyncRead + Send + Unpin>;
+pub type BoxedWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+pub const USER_AGENT: &str = concat!("rs4neo/", env!("CARGO_PKG_VERSION"));
+
+// A negotiated, authenticated Bolt connection. The transport is boxed so plain
+// TCP and wrapped streams look the same to everything above this layer.
+pub struct Connection {
+    stream: PackStream<BoxedReader, BoxedWriter>,
+    version: BoltVersion,
+    server_agent: String,
+    // Set while the server may still be sending records for a PULL nobody has
+    // read to the end yet.
+    streaming: bool,
+    // Set while a PULL is outstanding.
+    pulling: bool,
+    in_transaction: bool,
+    // Metadata of the SUCCESS that ended the last stream, for its summary.
+    stream_summary: Option<Dictionary>,
+    // Left by the last COMMIT or auto-commit query, until a session takes it.
+    bookmark: Option<String>,
+    // Set when a transaction was dropped while still open; it is rolled back
+    // before the next request.
+    abandoned: bool,
+    // Set once an I/O or protocol error leaves the stream in an unknown state.
+    broken: bool,
+}
+
+fn unexpected(response: Response) -> Error {
+    Error::protocol(format!("unexpected response {:?}", response))
+}
+
+impl Connection {
+    // Connects to `address` with the auth, encryption and timeout of `config`;
+    // its own host and port are ignored so routing can reuse one config.
+    pub async fn connect(address: &str, config: &Config) -> Result<Connection, Error> {
+        let connect = async {
+            let tcp = TcpStream::connect(address).await?;
+            tcp.set_nodelay(true)?;
+            let mut transport = tls::wrap(tcp, address, &config.encryption).await?;
+            #[cfg(any(test, feature = "chaos"))]
+            if let Some(chaos) = &config.chaos {
+                transport = Box::new(ChaosStream::new(transport, chaos.clone()));
+            }
+            let version = handshake(&mut transport, &DEFAULT_PROPOSALS).await?;
+            let (reader, writer) = tokio::io::split(transport);
+            let (reader, writer): (BoxedReader, BoxedWriter) = (Box::new(reader), Box::new(writer));
+            let routing = config.routing.then(|| routing_context(config));
+            let mut stream = PackStream::new(reader, writer);
+            stream.set_max_nesting_depth(config.max_nesting_depth);
+            let auth = &config.auth;
+            Connection::open(stream, version, auth, &config.user_agent, routing).await
+        };
+        match config.connect_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, connect).await {
+                Ok(result) => result,
+                Err(_) => Err(Error::Timeout(format!(
+                    "timed out connecting to {}",
+                    address
+                ))),
+            },
+            None => connect.await,
+        }
+    }
+
+    // Sends HELLO (and LOGON from Bolt 5.1) over an already negotiated transport.
+    // `routing` tells servers from Bolt 4.1 on that a routing driver connected.
+    pub async fn open(
+        stream: PackStream<BoxedReader, BoxedWriter>,
+        version: BoltVersion,
+        auth: &Auth,
+        user_agent: &str,
+        routing: Option<Dictionary>,
+    ) -> Result<Connection, Error> {
+        let mut connection = Connection {
+            stream,
+            version,
+            server_agent: String::new(),
+            streaming: false,
+            pulling: false,
+            in_transaction: false,
+            stream_summary: None,
+            bookmark: None,
+            abandoned: false,
+            broken: false,
+        };
+        let mut extra = Dictionary::new();
+        extra.insert(
+            "user_agent".to_string(),
+            MessageValue::String(user_agent.to_string()),
+        );
+        if version >= BoltVersion::new(5, 3) {
+            let agent = Dictionary::from([(
+                "product".to_string(),
+                MessageValue::String(USER_AGENT.to_string()),
+            )]);
+            extra.insert("bolt_agent".to_string(), MessageValue::Map(agent));
+        }
+        if let Some(routing) = routing.filter(|_| version >= BoltVersion::new(4, 1)) {
+            extra.insert("routing".to_string(), MessageValue::Map(routing));
+        }
+        let separate_logon = version >= BoltVersion::new(5, 1);
+        if !separate_logon {
+            extra.extend(auth.token());
+        }
+        let hello = connection.request(Hello { extra }).await?;
+        if let Some(MessageValue::String(agent)) = hello.metadata.get("server") {
+            connection.server_agent = agent.clone();
+        }
+        if separate_logon {
+            connection.request(Logon { auth: auth.token() }).await?;
+        }
+        Ok(connection)
+    }
+
+    pub fn version(&self) -> BoltVersion {
+        self.version
+    }
+
+    pub fn server_agent(&self) -> &str {
+        &self.server_agent
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+
+    // Whether the connection can serve a new request without cleaning up a
+    // stream or an open transaction first.
+    pub fn is_idle(&self) -> bool {
+        !self.broken && !self.streaming && !self.in_transaction
+    }
+
+    // False once a failure's RESET has rolled back the transaction on the
+    // server.
+    pub(crate) fn in_transaction(&self) -> bool {
+        self.in_transaction
+    }
+
+    pub async fn send<M: Into<MessageStructure>>(&mut self, message: M) -> Result<(), Error> {
+        self.broken = true;
+        self.stream.write_message(message.into()).await?;
+        self.stream.drain().await?;
+        self.broken = false;
+        Ok(())
+    }
+
+    pub async fn receive(&mut self) -> Result<Response, Error> {
+        self.broken = true;
+        let response = Response::try_from(self.stream.read_message().await?)?;
+        self.broken = false;
+        Ok(response)
+    }
+
+    // Sends a message that answers with a single summary and waits for it.
+    pub async fn request<M: Into<MessageStructure>>(
+        &mut self,
+        message: M,
+    ) -> Result<Success, Error> {
+        let message = message.into();
+        let tag = message.tag();
+        self.finish_stream().await?;
+        self.rollback_abandoned().await?;
+        self.send(message).await?;
+        let success = self.summary().await?;
+        match tag {
+            BEGIN => self.in_transaction = true,
+            COMMIT | ROLLBACK => self.in_transaction = false,
+            _ => {}
+        }
+        Ok(success)
+    }
+
+    // Reads the summary of the oldest outstanding request. A FAILURE puts the
+    // connection into the failed state, so it is reset before reporting it.
+    pub(crate) async fn summary(&mut self) -> Result<Success, Error> {
+        match self.receive().await? {
+            Response::Success(success) => {
+                self.keep_bookmark(&success);
+                Ok(success)
+            }
+            Response::Failure(failure) => Err(self.fail(failure).await),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    // A FAILURE ends any stream and leaves the connection failed until RESET.
+    async fn fail(&mut self, failure: Failure) -> Error {
+        self.streaming = false;
+        self.pulling = false;
+        match self.reset().await {
+            Ok(()) => failure.into(),
+            Err(e) => e,
+        }
+    }
+
+    // Marks the results of a successful RUN as waiting to be pulled.
+    pub(crate) fn start_stream(&mut self) {
+        self.streaming = true;
+        self.pulling = false;
+        self.stream_summary = None;
+    }
+
+    // The closing metadata of the stream that ended last, once.
+    pub(crate) fn take_stream_summary(&mut self) -> Option<Dictionary> {
+        self.stream_summary.take()
+    }
+
+    // Bolt 3 only has PULL_ALL and DISCARD_ALL, which share the tags of their
+    // successors but have no fields.
+    async fn send_pull(&mut self, n: i64) -> Result<(), Error> {
+        if self.version >= BoltVersion::new(4, 0) {
+            self.send(Pull::new(n)).await?;
+        } else {
+            self.send(MessageStructure::new(PULL, Vec::new())).await?;
+        }
+        self.pulling = true;
+        Ok(())
+    }
+
+    // Next record of the current stream, or None once it is exhausted. Records
+    // are pulled `fetch_size` at a time (-1 for all), asking for the next batch
+    // only when the previous one has been read.
+    pub(crate) async fn next_record(
+        &mut self,
+        fetch_size: i64,
+    ) -> Result<Option<Vec<MessageValue>>, Error> {
+        loop {
+            if !self.streaming {
+                return Ok(None);
+            }
+            if !self.pulling {
+                self.send_pull(fetch_size).await?;
+            }
+            match self.receive().await? {
+                Response::Record(record) => return Ok(Some(record.fields)),
+                Response::Success(success) => self.end_batch(&success),
+                Response::Failure(failure) => return Err(self.fail(failure).await),
+                other => return Err(unexpected(other)),
+            }
+        }
+    }
+
+    fn keep_bookmark(&mut self, success: &Success) {
+        if let Some(MessageValue::String(bookmark)) = success.metadata.get("bookmark") {
+            self.bookmark = Some(bookmark.clone());
+        }
+    }
+
+    pub(crate) fn take_bookmark(&mut self) -> Option<String> {
+        self.bookmark.take()
+    }
+
+    fn end_batch(&mut self, success: &Success) {
+        self.keep_bookmark(success);
+        self.pulling = false;
+        if success.metadata.get("has_more") != Some(&MessageValue::Bool(true)) {
+            self.streaming = false;
+            self.stream_summary = Some(success.metadata.clone());
+        }
+    }
+
+    // Skips whatever is left of a stream the caller stopped reading: the rest
+    // of a batch already asked for is read and dropped, and the server is told
+    // to discard anything not pulled yet.
+    pub(crate) async fn finish_stream(&mut self) -> Result<(), Error> {
+        while self.pulling {
+            match self.receive().await? {
+                Response::Record(_) => {}
+                Response::Success(success) => self.end_batch(&success),
+                Response::Failure(failure) => return Err(self.fail(failure).await),
+                other => return Err(unexpected(other)),
+            }
+        }
+        if self.streaming {
+            if self.version >= BoltVersion::new(4, 0) {
+                self.send(Discard::all()).await?;
+            } else {
+                self.send(MessageStructure::new(DISCARD, Vec::new()))
+                    .await?;
+            }
+            self.streaming = false;
+            self.stream_summary = Some(self.summary().await?.metadata);
+        }
+        Ok(())
+    }
+
+    // Called when a transaction goes away without being committed or rolled
+    // back, which can't be done from `Drop`.
+    pub(crate) fn abandon_transaction(&mut self) {
+        if self.in_transaction {
+            self.abandoned = true;
+        }
+    }
+
+    async fn rollback_abandoned(&mut self) -> Result<(), Error> {
+        if self.abandoned {
+            self.abandoned = false;
+            self.send(Rollback).await?;
+            self.summary().await?;
+            self.in_transaction = false;
+        }
+        Ok(())
+    }
+
+    // Clears a failed state and rolls back any open transaction. Anything still
+    // outstanding is answered with IGNORED before the RESET's own SUCCESS.
+    pub async fn reset(&mut self) -> Result<(), Error> {
+        self.send(Reset).await?;
+        loop {
+            match self.receive().await? {
+                Response::Success(_) => {
+                    self.in_transaction = false;
+                    self.abandoned = false;
+                    return Ok(());
+                }
+                Response::Ignored(_) | Response::Record(_) => {}
+                Response::Failure(failure) => return Err(failure.into()),
+            }
+        }
+    }
+
+    pub async fn close(mut self) -> Result<(), Error> {
+        self.send(super::messages::Goodbye).await?;
+        self.stream.close().await
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::error::Error;
+
+pub const MAGIC_PREAMBLE: [u8; 4] = [0x60, 0x60, 0xB0, 0x17];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoltVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl BoltVersion {
+    pub const V3_0: BoltVersion = BoltVersion::new(3, 0);
+    pub const V4_1: BoltVersion = BoltVersion::new(4, 1);
+    pub const V4_4: BoltVersion = BoltVersion::new(4, 4);
+    pub const V5_0: BoltVersion = BoltVersion::new(5, 0);
+    pub const V5_4: BoltVersion = BoltVersion::new(5, 4);
+
+    pub const fn new(major: u8, minor: u8) -> Self {
+        BoltVersion { major, minor }
+    }
+}
+
+impl fmt::Display for BoltVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+// One of the four handshake slots. From Bolt 4.3 a slot can cover a range of
+// minor versions: `version` and the `range` minor versions below it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VersionProposal {
+    pub version: BoltVersion,
+    pub range: u8,
+}
+
+impl VersionProposal {
+    pub const fn exact(version: BoltVersion) -> Self {
+        VersionProposal { version, range: 0 }
+    }
+
+    pub const fn range(version: BoltVersion, range: u8) -> Self {
+        VersionProposal { version, range }
+    }
+
+    pub fn encode(&self) -> [u8; 4] {
+        [0x00, self.range, self.version.minor, self.version.major]
+    }
+
+    pub fn contains(&self, version: BoltVersion) -> bool {
+        version.major == self.version.major
+            && version.minor <= self.version.minor
+            && version.minor >= self.version.minor.saturating_sub(self.range)
+    }
+}
+
+pub const DEFAULT_PROPOSALS: [VersionProposal; 4] = [
+    VersionProposal::range(BoltVersion::V5_4, 4),
+    VersionProposal::range(BoltVersion::V4_4, 2),
+    VersionProposal::exact(BoltVersion::V4_1),
+    VersionProposal::exact(BoltVersion::V3_0),
+];
+
+// Sends the preamble and up to four proposals (unused slots are zero), then
+// reads back the version the server picked.
+pub async fn handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    proposals: &[VersionProposal],
+) -> Result<BoltVersion, Error> {
+    if proposals.len() > 4 {
+        return Err(Error::config(
+            "at most four protocol versions can be proposed",
+        ));
+    }
+    let mut request = MAGIC_PREAMBLE.to_vec();
+    for slot in 0..4 {
+        match proposals.get(slot) {
+            Some(proposal) => request.extend(proposal.encode()),
+            None => request.extend([0x00; 4]),
+        }
+    }
+    stream.write_all(&request).await?;
+    stream.flush().await?;
+
+    let mut response = [0; 4];
+    stream.read_exact(&mut response).await?;
+    parse_response(response, proposals)
+}
+
+fn parse_response(response: [u8; 4], proposals: &[VersionProposal]) -> Result<BoltVersion, Error> {
+    if response == [0x00; 4] {
+        return Err(Error::protocol(
+            "server does not support any of the proposed protocol versions".to_string(),
+        ));
+    }
+    let version = BoltVersion::new(response[3], response[2]);
+    if response[..2] != [0x00, 0x00] || !proposals.iter().any(|p| p.contains(version)) {
+        return Err(Error::protocol(format!(
+            "server selected protocol version {:02X?}, which was not proposed",
+            response
+        )));
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn negotiate(response: [u8; 4]) -> (Result<BoltVersion, Error>, Vec<u8>) {
+        let (mut client, mut server) = tokio::io::duplex(64);
+        let server = tokio::spawn(async move {
+            let mut request = vec![0; 20];
+            server.read_exact(&mut request).await.unwrap();
+            server.write_all(&response).await.unwrap();
+            request
+        });
+        let result = handshake(&mut client, &DEFAULT_PROPOSALS).await;
+        (result, server.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn sends_preamble_and_range_encoded_proposals() {
+        let (result, request) = negotiate([0x00, 0x00, 0x04, 0x05]).await;
+        assert_eq!(result.unwrap(), BoltVersion::V5_4);
+        assert_eq!(
+            request,
+            [
+                0x60, 0x60, 0xB0, 0x17, 0x00, 0x04, 0x04, 0x05, 0x00, 0x02, 0x04, 0x04, 0x00, 0x00,
+                0x01, 0x04, 0x00, 0x00, 0x00, 0x03,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn accepts_versions_inside_a_range() {
+        let (result, _) = negotiate([0x00, 0x00, 0x02, 0x05]).await;
+        assert_eq!(result.unwrap(), BoltVersion::new(5, 2));
+        let (result, _) = negotiate([0x00, 0x00, 0x03, 0x04]).await;
+        assert_eq!(result.unwrap(), BoltVersion::new(4, 3));
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_and_unproposed_versions() {
+        let (result, _) = negotiate([0x00; 4]).await;
+        assert!(result.is_err());
+        let (result, _) = negotiate([0x00, 0x00, 0x00, 0x04]).await;
+        assert!(result.is_err());
+        let (result, _) = negotiate([0x00, 0x00, 0x00, 0x06]).await;
+        assert!(result.is_err());
+    }
+}
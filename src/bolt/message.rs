@@ -1,7 +1,11 @@
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::tcp::{ReadHalf, WriteHalf};
-use tokio::net::TcpStream;
-#[derive(Clone, PartialEq)]
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::error::Error;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum MessageValue {
     String(String),
     Bytes(Vec<u8>),
@@ -12,60 +16,329 @@ pub enum MessageValue {
     Float(f64),
     Bool(bool),
     Structure(MessageStructure),
+    List(Vec<MessageValue>),
+    Map(HashMap<String, MessageValue>),
     Null,
-    //TODO: Impl HashMap and Vec values (probably with generic types that impl clone)
 }
 
-#[derive(Clone, PartialEq)]
+impl MessageValue {
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            MessageValue::String(_) => "String",
+            MessageValue::Bytes(_) => "Bytes",
+            MessageValue::TinyInt(_)
+            | MessageValue::SmallInt(_)
+            | MessageValue::Int(_)
+            | MessageValue::BigInt(_) => "Integer",
+            MessageValue::Float(_) => "Float",
+            MessageValue::Bool(_) => "Boolean",
+            MessageValue::Structure(_) => "Structure",
+            MessageValue::List(_) => "List",
+            MessageValue::Map(_) => "Map",
+            MessageValue::Null => "Null",
+        }
+    }
+
+    // Widens any of the integer variants to i64.
+    pub fn as_i64(&self) -> Result<i64, Error> {
+        match self {
+            MessageValue::TinyInt(i) => Ok(*i as i64),
+            MessageValue::SmallInt(i) => Ok(*i as i64),
+            MessageValue::Int(i) => Ok(*i as i64),
+            MessageValue::BigInt(i) => Ok(*i),
+            _ => Err(Error::serialization("value is not an integer")),
+        }
+    }
+
+    // Accepts floats and integers, refusing integers that f64 can't hold exactly.
+    pub fn as_f64(&self) -> Result<f64, Error> {
+        match self {
+            MessageValue::Float(f) => Ok(*f),
+            // i128 so that values rounding up to 2^63 don't saturate back to i64::MAX
+            MessageValue::BigInt(i) if (*i as f64) as i128 != *i as i128 => Err(
+                Error::serialization(format!("{} cannot be represented exactly as a float", i)),
+            ),
+            _ => match self.as_i64() {
+                Ok(i) => Ok(i as f64),
+                Err(_) => Err(Error::serialization("value is not a number")),
+            },
+        }
+    }
+}
+
+// Bolt only has 64-bit floats, so f32 input is widened.
+impl From<f32> for MessageValue {
+    fn from(f: f32) -> Self {
+        MessageValue::Float(f as f64)
+    }
+}
+
+impl From<f64> for MessageValue {
+    fn from(f: f64) -> Self {
+        MessageValue::Float(f)
+    }
+}
+
+// Integers take the narrowest variant holding them, which packs smallest.
+impl From<i64> for MessageValue {
+    fn from(i: i64) -> Self {
+        if let Ok(i) = i8::try_from(i) {
+            MessageValue::TinyInt(i)
+        } else if let Ok(i) = i16::try_from(i) {
+            MessageValue::SmallInt(i)
+        } else if let Ok(i) = i32::try_from(i) {
+            MessageValue::Int(i)
+        } else {
+            MessageValue::BigInt(i)
+        }
+    }
+}
+
+macro_rules! from_integer {
+    ($($int:ty),*) => {
+        $(impl From<$int> for MessageValue {
+            fn from(i: $int) -> Self {
+                MessageValue::from(i64::from(i))
+            }
+        })*
+    };
+}
+
+from_integer!(i8, i16, i32, u8, u16, u32);
+
+impl From<bool> for MessageValue {
+    fn from(b: bool) -> Self {
+        MessageValue::Bool(b)
+    }
+}
+
+impl From<String> for MessageValue {
+    fn from(s: String) -> Self {
+        MessageValue::String(s)
+    }
+}
+
+impl From<&str> for MessageValue {
+    fn from(s: &str) -> Self {
+        MessageValue::String(s.to_string())
+    }
+}
+
+impl From<MessageStructure> for MessageValue {
+    fn from(structure: MessageStructure) -> Self {
+        MessageValue::Structure(structure)
+    }
+}
+
+// None is sent as null.
+impl<T: Into<MessageValue>> From<Option<T>> for MessageValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(MessageValue::Null, Into::into)
+    }
+}
+
+// A list; byte arrays need `MessageValue::Bytes`.
+impl<T: Into<MessageValue>> From<Vec<T>> for MessageValue {
+    fn from(list: Vec<T>) -> Self {
+        MessageValue::List(list.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<MessageValue>> From<HashMap<String, T>> for MessageValue {
+    fn from(map: HashMap<String, T>) -> Self {
+        MessageValue::Map(map.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+fn mismatch(expected: &str, value: &MessageValue) -> Error {
+    Error::serialization(format!("expected {}, got {}", expected, value.type_name()))
+}
+
+impl TryFrom<MessageValue> for i64 {
+    type Error = Error;
+
+    fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+        value.as_i64().map_err(|_| mismatch("Integer", &value))
+    }
+}
+
+macro_rules! try_from_integer {
+    ($($int:ty),*) => {
+        $(impl TryFrom<MessageValue> for $int {
+            type Error = Error;
+
+            fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+                let i = i64::try_from(value)?;
+                <$int>::try_from(i).map_err(|_| {
+                    Error::serialization(format!(
+                        "{} is out of range for {}",
+                        i,
+                        stringify!($int)
+                    ))
+                })
+            }
+        })*
+    };
+}
+
+try_from_integer!(i8, i16, i32, u8, u16, u32, u64);
+
+impl TryFrom<MessageValue> for f64 {
+    type Error = Error;
+
+    fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+        value.as_f64()
+    }
+}
+
+impl TryFrom<MessageValue> for bool {
+    type Error = Error;
+
+    fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+        match value {
+            MessageValue::Bool(b) => Ok(b),
+            other => Err(mismatch("Boolean", &other)),
+        }
+    }
+}
+
+impl TryFrom<MessageValue> for String {
+    type Error = Error;
+
+    fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+        match value {
+            MessageValue::String(s) => Ok(s),
+            other => Err(mismatch("String", &other)),
+        }
+    }
+}
+
+// Null reads as None.
+impl<T: TryFrom<MessageValue, Error = Error>> TryFrom<MessageValue> for Option<T> {
+    type Error = Error;
+
+    fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+        match value {
+            MessageValue::Null => Ok(None),
+            value => T::try_from(value).map(Some),
+        }
+    }
+}
+
+// Also reads Bytes, when T is u8.
+impl<T: TryFrom<MessageValue, Error = Error>> TryFrom<MessageValue> for Vec<T> {
+    type Error = Error;
+
+    fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+        match value {
+            MessageValue::List(list) => list.into_iter().map(T::try_from).collect(),
+            MessageValue::Bytes(bytes) => bytes
+                .into_iter()
+                .map(|b| T::try_from(MessageValue::from(b)))
+                .collect(),
+            other => Err(mismatch("List", &other)),
+        }
+    }
+}
+
+impl<T: TryFrom<MessageValue, Error = Error>> TryFrom<MessageValue> for HashMap<String, T> {
+    type Error = Error;
+
+    fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+        match value {
+            MessageValue::Map(map) => map
+                .into_iter()
+                .map(|(k, v)| Ok((k, T::try_from(v)?)))
+                .collect(),
+            other => Err(mismatch("Map", &other)),
+        }
+    }
+}
+
+// Builds a parameter map; values are anything with `Into<MessageValue>`:
+// `params! {"name" => "Alice", "age" => 42}`.
+#[macro_export]
+macro_rules! params {
+    () => {
+        $crate::bolt::messages::Dictionary::new()
+    };
+    ($($key:expr => $value:expr),+ $(,)?) => {{
+        let mut params = $crate::bolt::messages::Dictionary::new();
+        $(params.insert(
+            ::std::string::String::from($key),
+            $crate::bolt::message::MessageValue::from($value),
+        );)+
+        params
+    }};
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct MessageStructure {
     tag: u8,
     fields: Vec<MessageValue>,
 }
 
 impl MessageStructure {
-    fn new(tag: u8, fields: Vec<MessageValue>) -> MessageStructure {
-        MessageStructure {
-            tag: tag,
-            fields: fields,
-        }
+    pub fn new(tag: u8, fields: Vec<MessageValue>) -> MessageStructure {
+        MessageStructure { tag, fields }
+    }
+    pub fn tag(&self) -> u8 {
+        self.tag
     }
-    fn __eq__(&self, other: &MessageStructure) -> bool {
-        self.tag == other.tag && self.fields == other.fields
+    pub fn fields(&self) -> &[MessageValue] {
+        &self.fields
     }
-    fn __ne__(&self, other: &MessageStructure) -> bool {
-        self.tag != other.tag || self.fields != other.fields
+    pub fn into_fields(self) -> Vec<MessageValue> {
+        self.fields
     }
-    fn __len__(&self) -> usize {
+    pub fn iter(&self) -> std::slice::Iter<'_, MessageValue> {
+        self.fields.iter()
+    }
+    pub fn len(&self) -> usize {
         self.fields.len()
     }
-    fn __getitem__(&self, index: usize) -> MessageValue {
-        self.fields[index].clone()
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
     }
-    fn __setitem__(&mut self, index: usize, value: MessageValue) {
-        self.fields[index] = value;
+}
+
+impl Index<usize> for MessageStructure {
+    type Output = MessageValue;
+
+    fn index(&self, index: usize) -> &MessageValue {
+        &self.fields[index]
+    }
+}
+
+impl IndexMut<usize> for MessageStructure {
+    fn index_mut(&mut self, index: usize) -> &mut MessageValue {
+        &mut self.fields[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a MessageStructure {
+    type Item = &'a MessageValue;
+    type IntoIter = std::slice::Iter<'a, MessageValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.fields.iter()
     }
 }
 
 struct MessageBuffer {
     buffer: Vec<u8>,
-    cursor: usize,
 }
 
 impl MessageBuffer {
     fn new(capacity: usize) -> Self {
         MessageBuffer {
-            buffer: vec![0; capacity],
-            cursor: 0,
+            buffer: Vec::with_capacity(capacity),
         }
     }
 
-    fn try_write(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
-        let len = self.buffer.len();
-        let remaining = len - self.cursor;
-        let written = data.len().min(remaining);
-        self.buffer[self.cursor..self.cursor + written].copy_from_slice(&data[..written]);
-        self.cursor += written;
-        Ok(written)
+    fn try_write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        self.buffer.extend_from_slice(data);
+        Ok(data.len())
     }
 }
 
@@ -75,15 +348,10 @@ struct Packer {
 
 impl Packer {
     pub fn new(stream: MessageBuffer) -> Packer {
-        Packer { stream: stream }
-    }
-
-    fn pack_raw(&mut self, data: &[u8]) -> Result<(), std::io::Error> {
-        self.stream.try_write(data)?;
-        Ok(())
+        Packer { stream }
     }
 
-    fn pack_struct(&mut self, sig: u8, fields: Vec<MessageValue>) -> Result<(), std::io::Error> {
+    fn pack_struct(&mut self, sig: u8, fields: Vec<MessageValue>) -> Result<(), Error> {
         let size = fields.len();
         match size {
             0x00 => {
@@ -135,7 +403,10 @@ impl Packer {
                 self.stream.try_write(b"\xBF")?;
             }
             _ => {
-                panic!("Struct size overflow");
+                return Err(Error::serialization(format!(
+                    "a structure can have at most 15 fields, got {}",
+                    size
+                )));
             }
         }
         self.stream.try_write(&[sig])?;
@@ -145,7 +416,7 @@ impl Packer {
         Ok(())
     }
 
-    fn pack_string_header(&mut self, length: usize) -> Result<(), std::io::Error> {
+    fn pack_string_header(&mut self, length: usize) -> Result<(), Error> {
         match length {
             0x00 => {
                 self.stream.try_write(b"\x80")?;
@@ -195,43 +466,119 @@ impl Packer {
             0x0F => {
                 self.stream.try_write(b"\x8F")?;
             }
-            0x00..=0xF => {
+            0x10..=0xFF => {
                 self.stream.try_write(b"\xD0")?;
-                self.stream.try_write(length.to_be_bytes().as_ref())?;
+                self.stream
+                    .try_write((length as u8).to_be_bytes().as_ref())?;
             }
             0x100..=0xFFFF => {
                 self.stream.try_write(b"\xD1")?;
-                self.stream.try_write(length.to_be_bytes().as_ref())?;
+                self.stream
+                    .try_write((length as u16).to_be_bytes().as_ref())?;
             }
             0x10000..=0xFFFFFFFF => {
                 self.stream.try_write(b"\xD2")?;
-                self.stream.try_write(length.to_be_bytes().as_ref())?;
+                self.stream
+                    .try_write((length as u32).to_be_bytes().as_ref())?;
+            }
+            _ => {
+                return Err(Error::serialization(format!(
+                    "a string of {} bytes is too long to pack",
+                    length
+                )));
             }
-            _ => panic!("String header size overflow"),
         }
         Ok(())
     }
 
-    fn pack_bytes_header(&mut self, length: usize) -> Result<(), std::io::Error> {
+    fn pack_bytes_header(&mut self, length: usize) -> Result<(), Error> {
         match length {
             0x00..=0xFF => {
                 self.stream.try_write(b"\xCC")?;
-                self.stream.try_write(length.to_be_bytes().as_ref())?;
+                self.stream
+                    .try_write((length as u8).to_be_bytes().as_ref())?;
             }
             0x100..=0xFFFF => {
                 self.stream.try_write(b"\xCD")?;
-                self.stream.try_write(length.to_be_bytes().as_ref())?;
+                self.stream
+                    .try_write((length as u16).to_be_bytes().as_ref())?;
             }
             0x10000..=0xFFFFFFFF => {
                 self.stream.try_write(b"\xCE")?;
-                self.stream.try_write(length.to_be_bytes().as_ref())?;
+                self.stream
+                    .try_write((length as u32).to_be_bytes().as_ref())?;
+            }
+            _ => {
+                return Err(Error::serialization(format!(
+                    "a byte array of {} bytes is too long to pack",
+                    length
+                )));
             }
-            _ => panic!("Bytes header size overflow"),
         }
         Ok(())
     }
 
-    pub fn pack(&mut self, val: MessageValue) -> Result<(), std::io::Error> {
+    fn pack_list_header(&mut self, length: usize) -> Result<(), Error> {
+        match length {
+            0x00..=0x0F => {
+                self.stream.try_write(&[0x90 | length as u8])?;
+            }
+            0x10..=0xFF => {
+                self.stream.try_write(b"\xD4")?;
+                self.stream
+                    .try_write((length as u8).to_be_bytes().as_ref())?;
+            }
+            0x100..=0xFFFF => {
+                self.stream.try_write(b"\xD5")?;
+                self.stream
+                    .try_write((length as u16).to_be_bytes().as_ref())?;
+            }
+            0x10000..=0xFFFFFFFF => {
+                self.stream.try_write(b"\xD6")?;
+                self.stream
+                    .try_write((length as u32).to_be_bytes().as_ref())?;
+            }
+            _ => {
+                return Err(Error::serialization(format!(
+                    "a list of {} items is too long to pack",
+                    length
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn pack_map_header(&mut self, length: usize) -> Result<(), Error> {
+        match length {
+            0x00..=0x0F => {
+                self.stream.try_write(&[0xA0 | length as u8])?;
+            }
+            0x10..=0xFF => {
+                self.stream.try_write(b"\xD8")?;
+                self.stream
+                    .try_write((length as u8).to_be_bytes().as_ref())?;
+            }
+            0x100..=0xFFFF => {
+                self.stream.try_write(b"\xD9")?;
+                self.stream
+                    .try_write((length as u16).to_be_bytes().as_ref())?;
+            }
+            0x10000..=0xFFFFFFFF => {
+                self.stream.try_write(b"\xDA")?;
+                self.stream
+                    .try_write((length as u32).to_be_bytes().as_ref())?;
+            }
+            _ => {
+                return Err(Error::serialization(format!(
+                    "a map of {} entries is too long to pack",
+                    length
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn pack(&mut self, val: MessageValue) -> Result<(), Error> {
         match val {
             MessageValue::Null => {
                 self.stream.try_write(b"\xC0")?;
@@ -279,14 +626,22 @@ impl Packer {
                 self.stream.try_write(&b[..])?;
             }
             MessageValue::Structure(s) => self.pack_struct(s.tag, s.fields)?,
+            MessageValue::List(items) => {
+                self.pack_list_header(items.len())?;
+                for item in items {
+                    self.pack(item)?;
+                }
+            }
+            MessageValue::Map(entries) => {
+                self.pack_map_header(entries.len())?;
+                for (key, value) in entries {
+                    self.pack(MessageValue::String(key))?;
+                    self.pack(value)?;
+                }
+            }
         }
         Ok(())
     }
-
-    fn pack_end_of_stream(&mut self) -> Result<(), std::io::Error> {
-        self.stream.try_write(b"\xDF")?;
-        Ok(())
-    }
 }
 
 struct UnpackableBuffer {
@@ -318,22 +673,17 @@ impl UnpackableBuffer {
         self.pos = 0;
     }
 
-    fn read(&mut self, n: usize) -> Result<&[u8], std::io::Error> {
-        if self.pos + n > self.buffer.len() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::UnexpectedEof,
-                "unpackable buffer exhausted",
-            ));
+    fn read(&mut self, n: usize) -> Result<&[u8], Error> {
+        if self.pos + n > self.used {
+            return Err(Error::protocol("unpackable buffer exhausted"));
         }
         let result = &self.buffer[self.pos..self.pos + n];
         self.pos += n;
         Ok(result)
     }
 
-    fn read_u8(&mut self) -> Result<u8, std::io::Error> {
-        let result = self.buffer[self.pos];
-        self.pos += 1;
-        Ok(result)
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read(1)?[0])
     }
 
     fn pop_u16(&mut self) -> u16 {
@@ -343,28 +693,32 @@ impl UnpackableBuffer {
             self.used -= 2;
             result
         } else {
-            return 0;
+            0
         }
     }
     fn resize_buffer(&mut self, new_size: usize) {
-        let mut new_buffer = vec![0; new_size];
-        new_buffer.copy_from_slice(&self.buffer[..self.used]);
-        self.buffer = new_buffer;
-        self.used = self.buffer.len();
+        self.buffer.resize(new_size, 0);
     }
 
-    fn receive(&mut self, sock: &mut ReadHalf, n_bytes: usize) -> Result<(), std::io::Error> {
-        let end = self.used + n_bytes;
+    // Reads from the socket straight into the buffer until `end` bytes are in
+    // use. Progress is kept in `used`, so calling again with the same `end`
+    // after a cancelled call picks up where it left off.
+    async fn receive<R: AsyncRead + Unpin>(
+        &mut self,
+        sock: &mut R,
+        end: usize,
+    ) -> Result<(), Error> {
         if end > self.buffer.len() {
             self.resize_buffer(end);
         }
         while self.used < end {
-            let n = sock.try_read(&mut self.buffer[self.used..end])?;
+            let n = sock.read(&mut self.buffer[self.used..end]).await?;
             if n == 0 {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::UnexpectedEof,
                     "buffer exhausted",
-                ));
+                )
+                .into());
             }
             self.used += n;
         }
@@ -372,222 +726,570 @@ impl UnpackableBuffer {
     }
 }
 
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 100;
+
 struct Unpacker {
     unpackable: UnpackableBuffer,
+    max_depth: usize,
+    depth: usize,
 }
 
 impl Unpacker {
     pub fn new(unpackable: UnpackableBuffer) -> Self {
-        Self { unpackable }
+        Self {
+            unpackable,
+            max_depth: DEFAULT_MAX_NESTING_DEPTH,
+            depth: 0,
+        }
     }
     pub fn reset(&mut self) {
         self.unpackable.reset();
+        self.depth = 0;
     }
-    pub fn read(&mut self, n: usize) -> Result<&[u8], std::io::Error> {
-        return Ok(self.unpackable.read(n)?);
+    pub fn read(&mut self, n: usize) -> Result<&[u8], Error> {
+        self.unpackable.read(n)
     }
-    pub fn read_u8(&mut self) -> Result<u8, std::io::Error> {
-        return Ok(self.unpackable.read_u8()?);
+    pub fn read_u8(&mut self) -> Result<u8, Error> {
+        self.unpackable.read_u8()
     }
-    pub fn unpack(&mut self) -> Result<MessageValue, std::io::Error> {
+    pub fn unpack(&mut self) -> Result<MessageValue, Error> {
         let marker = self.read_u8()?;
         let marker_high = marker & 0xF0;
         match marker {
             //null
-            0xC0 => {
-                return Ok(MessageValue::Null);
-            }
+            0xC0 => Ok(MessageValue::Null),
             //bool
-            0xC2 => {
-                return Ok(MessageValue::Bool(false));
-            }
-            0xC3 => {
-                return Ok(MessageValue::Bool(true));
-            }
+            0xC2 => Ok(MessageValue::Bool(false)),
+            0xC3 => Ok(MessageValue::Bool(true)),
             //float
             0xC1 => {
                 let val = self.read(8)?;
                 let f = f64::from_be_bytes(val.try_into().unwrap());
-                return Ok(MessageValue::Float(f));
+                Ok(MessageValue::Float(f))
             }
             // tiny int
-            0x00..=0x7f => {
-                return Ok(MessageValue::TinyInt(marker as i8));
-            }
-            0xF0..=0xFF => {
-                return Ok(MessageValue::TinyInt(marker as i8));
-            }
+            0x00..=0x7f => Ok(MessageValue::TinyInt(marker as i8)),
+            0xF0..=0xFF => Ok(MessageValue::TinyInt(marker as i8)),
             0xc8 => {
                 let val = self.read(1)?;
                 let i = i8::from_be_bytes(val.try_into().unwrap());
-                return Ok(MessageValue::TinyInt(i));
+                Ok(MessageValue::TinyInt(i))
             }
             // small int
             0xC9 => {
                 let val = self.read(2)?;
                 let i = i16::from_be_bytes(val.try_into().unwrap());
-                return Ok(MessageValue::SmallInt(i));
+                Ok(MessageValue::SmallInt(i))
             }
             // int
             0xCA => {
                 let val = self.read(4)?;
                 let i = i32::from_be_bytes(val.try_into().unwrap());
-                return Ok(MessageValue::Int(i));
+                Ok(MessageValue::Int(i))
             }
             // big int
             0xCB => {
                 let val = self.read(8)?;
                 let i = i64::from_be_bytes(val.try_into().unwrap());
-                return Ok(MessageValue::BigInt(i));
+                Ok(MessageValue::BigInt(i))
             }
             // bytes
             0xCC => {
-                let size = self.read(1)?.as_ptr();
-                return Ok(MessageValue::Bytes(self.read(size as usize)?.to_vec()));
+                let size = self.read_size(1)?;
+                Ok(MessageValue::Bytes(self.read(size)?.to_vec()))
             }
             0xCD => {
-                let size = self.read(2)?.as_ptr();
-                return Ok(MessageValue::Bytes(self.read(size as usize)?.to_vec()));
+                let size = self.read_size(2)?;
+                Ok(MessageValue::Bytes(self.read(size)?.to_vec()))
             }
             0xCE => {
-                let size = self.read(4)?.as_ptr();
-                return Ok(MessageValue::Bytes(self.read(size as usize)?.to_vec()));
+                let size = self.read_size(4)?;
+                Ok(MessageValue::Bytes(self.read(size)?.to_vec()))
             }
             // string
             0xD0 => {
-                let size = self.read(1)?.as_ptr();
-                let string_bytes = self.read(size as usize)?;
-                return Ok(MessageValue::String(
-                    String::from_utf8(string_bytes.to_vec()).unwrap(),
-                ));
+                let size = self.read_size(1)?;
+                self.read_string(size)
             }
             0xD1 => {
-                let size = self.read(2)?.as_ptr();
-                let string_bytes = self.read(size as usize)?;
-                return Ok(MessageValue::String(
-                    String::from_utf8(string_bytes.to_vec()).unwrap(),
-                ));
+                let size = self.read_size(2)?;
+                self.read_string(size)
             }
             0xD2 => {
-                let size = self.read(4)?.as_ptr();
-                let string_bytes = self.read(size as usize)?;
-                return Ok(MessageValue::String(
-                    String::from_utf8(string_bytes.to_vec()).unwrap(),
-                ));
+                let size = self.read_size(4)?;
+                self.read_string(size)
+            }
+            // list
+            0x90..=0x9F => {
+                let size = (marker & 0x0F) as usize;
+                Ok(MessageValue::List(self.unpack_nested(size)?))
+            }
+            0xD4 => {
+                let size = self.read_size(1)?;
+                Ok(MessageValue::List(self.unpack_nested(size)?))
+            }
+            0xD5 => {
+                let size = self.read_size(2)?;
+                Ok(MessageValue::List(self.unpack_nested(size)?))
+            }
+            0xD6 => {
+                let size = self.read_size(4)?;
+                Ok(MessageValue::List(self.unpack_nested(size)?))
+            }
+            // map
+            0xA0..=0xAF => {
+                let size = (marker & 0x0F) as usize;
+                self.unpack_map(size)
+            }
+            0xD8 => {
+                let size = self.read_size(1)?;
+                self.unpack_map(size)
+            }
+            0xD9 => {
+                let size = self.read_size(2)?;
+                self.unpack_map(size)
+            }
+            0xDA => {
+                let size = self.read_size(4)?;
+                self.unpack_map(size)
             }
             // structure
             0xB0..=0xBF => {
                 let (size, tag) = self._unpack_structure_header(marker)?;
-                let mut value = MessageStructure::new(tag, Vec::new());
-                for _ in 0..size {
-                    value.fields.push(self.unpack()?);
-                }
-                return Ok(MessageValue::Structure(value));
+                let fields = self.unpack_nested(size as usize)?;
+                Ok(MessageValue::Structure(MessageStructure::new(tag, fields)))
             }
             _ => {
                 //tiny string
                 if marker_high == 0x80 {
                     let size = marker & 0x0F;
-                    let string_bytes = self.read(size as usize)?;
-                    return Ok(MessageValue::String(
-                        String::from_utf8(string_bytes.to_vec()).unwrap(),
-                    ));
+                    self.read_string(size as usize)
                 } else {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        "unpackable buffer exhausted",
-                    ));
+                    Err(Error::protocol(format!("unknown marker 0x{:02X}", marker)))
                 }
             }
         }
     }
 
-    fn unpack_structure_header(&mut self) -> Result<(u8, u8), std::io::Error> {
-        let marker = self.read_u8()?;
-        return self._unpack_structure_header(marker);
+    // Unpacks the `size` values of a container one level further down. The
+    // unpacker recurses once per level, so the depth limit is what keeps a
+    // hostile or broken server from overflowing the stack.
+    fn unpack_nested(&mut self, size: usize) -> Result<Vec<MessageValue>, Error> {
+        if self.depth >= self.max_depth {
+            return Err(Error::NestingTooDeep {
+                max_depth: self.max_depth,
+            });
+        }
+        self.depth += 1;
+        // every value takes at least a byte, so a bogus size can't force a huge
+        // allocation up front
+        let remaining = self.unpackable.used - self.unpackable.pos;
+        let mut values = Vec::with_capacity(size.min(remaining));
+        let mut result = Ok(());
+        for _ in 0..size {
+            match self.unpack() {
+                Ok(value) => values.push(value),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.depth -= 1;
+        result.map(|_| values)
+    }
+
+    // Bolt only allows string keys; anything else means the server sent junk.
+    fn unpack_map(&mut self, size: usize) -> Result<MessageValue, Error> {
+        let mut entries = self.unpack_nested(size * 2)?.into_iter();
+        let mut map = HashMap::with_capacity(size);
+        while let (Some(key), Some(value)) = (entries.next(), entries.next()) {
+            match key {
+                MessageValue::String(key) => {
+                    map.insert(key, value);
+                }
+                other => {
+                    return Err(Error::protocol(format!(
+                        "map keys must be strings, got {}",
+                        other.type_name()
+                    )));
+                }
+            }
+        }
+        Ok(MessageValue::Map(map))
     }
 
-    fn _unpack_structure_header(&mut self, marker: u8) -> Result<(u8, u8), std::io::Error> {
+    // Reads a big-endian unsigned length of `n_bytes` width, as found after the
+    // sized string, bytes and collection markers.
+    fn read_size(&mut self, n_bytes: usize) -> Result<usize, Error> {
+        let size = self
+            .read(n_bytes)?
+            .iter()
+            .fold(0usize, |size, byte| (size << 8) | *byte as usize);
+        Ok(size)
+    }
+
+    fn read_string(&mut self, size: usize) -> Result<MessageValue, Error> {
+        let string_bytes = self.read(size)?.to_vec();
+        match String::from_utf8(string_bytes) {
+            Ok(s) => Ok(MessageValue::String(s)),
+            Err(e) => Err(Error::protocol(e.to_string())),
+        }
+    }
+
+    fn _unpack_structure_header(&mut self, marker: u8) -> Result<(u8, u8), Error> {
         let marker_high = marker & 0xF0;
         match marker_high {
             0xB0 => {
                 let sig = self.read(1)?;
                 let size = marker & 0x0F;
-                return Ok((size, sig[0]));
-            }
-            _ => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "unpackable buffer exhausted",
-                ));
+                Ok((size, sig[0]))
             }
+            _ => Err(Error::protocol(format!(
+                "expected a structure, got marker 0x{:02X}",
+                marker
+            ))),
         }
     }
 }
 
-struct PackStream<'a> {
-    reader: ReadHalf<'a>,
-    writer: WriteHalf<'a>,
+pub fn pack(value: MessageValue) -> Result<Vec<u8>, Error> {
+    let mut packer = Packer::new(MessageBuffer::new(8192));
+    packer.pack(value)?;
+    Ok(packer.stream.buffer)
 }
 
-impl<'a> PackStream<'a> {
-    pub fn new(stream: &'a mut TcpStream) -> Self {
-        let (reader, writer) = stream.split();
-        return Self { reader, writer };
+// Unpacks a single value from an already dechunked message body.
+pub fn unpack(data: &[u8]) -> Result<MessageValue, Error> {
+    let mut unpacker = Unpacker::new(UnpackableBuffer::new(Some(data.to_vec())));
+    unpacker.unpack()
+}
+
+pub const MAX_CHUNK_SIZE: usize = 0xFFFF;
+
+// Frames a packed message as chunks of at most MAX_CHUNK_SIZE bytes, each
+// behind its big-endian length, closed by an empty chunk.
+fn chunk(data: &[u8]) -> Vec<u8> {
+    let chunks = data.len().div_ceil(MAX_CHUNK_SIZE);
+    let mut framed = Vec::with_capacity(data.len() + 2 * chunks + 2);
+    for chunk in data.chunks(MAX_CHUNK_SIZE) {
+        framed.extend((chunk.len() as u16).to_be_bytes());
+        framed.extend_from_slice(chunk);
     }
+    framed.extend([0x00, 0x00]);
+    framed
+}
 
-    pub async fn read_message(&mut self) -> Result<MessageValue, std::io::Error> {
-        let mut data = Vec::new();
-        let mut more = true;
-        while more {
-            let mut head_buf = [0; 2];
-            let chunk_header = self.reader.read(&mut head_buf).await;
-            match chunk_header {
-                Ok(2) => {
-                    let chunk_size = u16::from_be_bytes(head_buf.try_into().unwrap());
-                    if chunk_size != 0 {
-                        let chunk_size = chunk_size as usize;
-                        let mut chunk_buf = vec![0; chunk_size];
-                        let _chunk_read = self.reader.read(&mut chunk_buf).await;
-                        data.copy_from_slice(chunk_buf.as_ref());
-                    }
-                }
-                Ok(_) => {
-                    more = false;
-                }
-                Err(e) => {
-                    println!("{:?}", e);
-                    more = false;
-                }
+// The framing core only needs the async read/write traits, which don't tie it to
+// the tokio runtime; transport specific constructors live in their own modules.
+pub struct PackStream<R, W> {
+    reader: R,
+    writer: W,
+    unpacker: Unpacker,
+    receive_until: usize,
+}
+
+impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> PackStream<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            reader,
+            writer,
+            unpacker: Unpacker::new(UnpackableBuffer::new(None)),
+            receive_until: 2,
+        }
+    }
+
+    pub fn set_max_nesting_depth(&mut self, max_depth: usize) {
+        self.unpacker.max_depth = max_depth;
+    }
+
+    // Chunks are received directly into the connection's unpack buffer, each one
+    // together with the header of the chunk after it; the header is then popped
+    // back off the end. The buffer is reset and reused for the next message.
+    //
+    // All progress lives in the buffer and `receive_until`, so if this future is
+    // dropped mid-frame the next call carries on with the same message. Nothing
+    // past the next chunk header is read ahead, so there is never a tail to
+    // rescan or shift down for the following message.
+    pub async fn read_message(&mut self) -> Result<MessageValue, Error> {
+        loop {
+            let buffer = &mut self.unpacker.unpackable;
+            buffer.receive(&mut self.reader, self.receive_until).await?;
+            let chunk_size = buffer.pop_u16() as usize;
+            if chunk_size != 0 {
+                self.receive_until = buffer.used + chunk_size + 2;
+            } else if buffer.used == 0 {
+                // NOOP keep-alive between messages
+                self.receive_until = 2;
+            } else {
+                self.receive_until = 2;
+                let message = self.unpacker.unpack();
+                self.unpacker.reset();
+                return message;
             }
         }
-        let unpack_buf = UnpackableBuffer::new(Some(data));
-        let mut unpacker = Unpacker::new(unpack_buf);
-        return unpacker.unpack();
     }
 
-    pub async fn write_message(&mut self, message: MessageStructure) -> Result<(), std::io::Error> {
-        let mut packer = Packer::new(MessageBuffer::new(8192));
-        packer.pack(MessageValue::Structure(message))?;
-        let data = packer.stream.buffer;
-        let term = vec![0x00, 0x00];
-        let div = data.len() / 0x100;
-        let modu = data.len() % 0x100;
-        let mut b_msg = vec![div as u8, modu as u8];
-        b_msg.extend(data);
-        b_msg.extend(term);
-        let _write = self.writer.write(&b_msg).await;
+    // Writes the whole message before returning; it still has to be drained
+    // to reach the server.
+    pub async fn write_message(&mut self, message: MessageStructure) -> Result<(), Error> {
+        let data = pack(MessageValue::Structure(message))?;
+        self.writer.write_all(&chunk(&data)).await?;
         Ok(())
     }
 
-    pub async fn drain(&mut self) -> Result<(), std::io::Error> {
+    pub async fn drain(&mut self) -> Result<(), Error> {
         self.writer.flush().await?;
         Ok(())
     }
 
-    pub async fn close(&mut self) -> Result<(), std::io::Error> {
+    pub async fn close(&mut self) -> Result<(), Error> {
         self.writer.flush().await?;
         self.writer.shutdown().await?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+
+    #[test]
+    fn structure_fields_are_indexable() {
+        let mut structure =
+            MessageStructure::new(0x71, vec![MessageValue::Null, MessageValue::Bool(true)]);
+        structure[0] = MessageValue::TinyInt(3);
+        assert_eq!(structure.tag(), 0x71);
+        assert_eq!(structure.len(), 2);
+        assert!(structure[0] == MessageValue::TinyInt(3));
+        assert!(structure.iter().eq(structure.fields().iter()));
+    }
+
+    #[test]
+    fn integers_widen_to_i64_and_f64() {
+        assert_eq!(MessageValue::TinyInt(-3).as_i64().unwrap(), -3);
+        assert_eq!(MessageValue::SmallInt(1234).as_i64().unwrap(), 1234);
+        assert_eq!(MessageValue::Int(-70000).as_f64().unwrap(), -70000.0);
+        assert_eq!(
+            MessageValue::BigInt(1 << 53).as_f64().unwrap(),
+            9007199254740992.0
+        );
+        assert!(MessageValue::BigInt((1 << 53) + 1).as_f64().is_err());
+        assert!(MessageValue::BigInt(i64::MAX).as_f64().is_err());
+        assert_eq!(
+            MessageValue::BigInt(i64::MIN).as_f64().unwrap(),
+            -9223372036854775808.0
+        );
+        assert!(MessageValue::Float(1.5).as_i64().is_err());
+        assert!(MessageValue::Null.as_f64().is_err());
+        assert!(MessageValue::from(0.5f32) == MessageValue::Float(0.5));
+    }
+
+    #[test]
+    fn converts_to_and_from_rust_values() {
+        assert_eq!(MessageValue::from(42), MessageValue::TinyInt(42));
+        assert_eq!(MessageValue::from(300u16), MessageValue::SmallInt(300));
+        assert_eq!(
+            MessageValue::from(1i64 << 40),
+            MessageValue::BigInt(1 << 40)
+        );
+        assert_eq!(MessageValue::from(None::<&str>), MessageValue::Null);
+        assert_eq!(
+            MessageValue::from(vec!["a", "b"]),
+            MessageValue::List(vec!["a".into(), "b".into()])
+        );
+
+        assert_eq!(u8::try_from(MessageValue::SmallInt(200)).unwrap(), 200);
+        assert!(u8::try_from(MessageValue::SmallInt(300)).is_err());
+        assert!(i64::try_from(MessageValue::Float(1.0)).is_err());
+        assert_eq!(f64::try_from(MessageValue::TinyInt(2)).unwrap(), 2.0);
+        let names = MessageValue::List(vec!["a".into(), MessageValue::Null]);
+        let names: Vec<Option<String>> = names.try_into().unwrap();
+        assert_eq!(names, [Some("a".to_string()), None]);
+        let bytes = Vec::<u8>::try_from(MessageValue::Bytes(vec![1, 2])).unwrap();
+        assert_eq!(bytes, [1, 2]);
+        let map = HashMap::from([("n".to_string(), 1i64)]);
+        assert_eq!(
+            HashMap::<String, i64>::try_from(MessageValue::from(map.clone())).unwrap(),
+            map
+        );
+        assert!(String::try_from(MessageValue::Bool(true)).is_err());
+    }
+
+    #[test]
+    fn params_macro_builds_dictionaries() {
+        let params = crate::params! {"name" => "Alice", "age" => 42, "email" => None::<String>,};
+        assert_eq!(params.len(), 3);
+        assert_eq!(params["name"], MessageValue::String("Alice".to_string()));
+        assert_eq!(params["age"], MessageValue::TinyInt(42));
+        assert_eq!(params["email"], MessageValue::Null);
+        assert!(crate::params! {}.is_empty());
+    }
+
+    fn nested_structures(depth: usize) -> Vec<u8> {
+        let mut data = [0xB1, 0x71].repeat(depth);
+        data.push(0xC0);
+        data
+    }
+
+    #[test]
+    fn unpack_limits_nesting_depth() {
+        assert!(unpack(&nested_structures(DEFAULT_MAX_NESTING_DEPTH)).is_ok());
+        let err = unpack(&nested_structures(DEFAULT_MAX_NESTING_DEPTH + 1))
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::NestingTooDeep { max_depth: 100 }));
+        assert!(unpack(&nested_structures(100_000)).is_err());
+    }
+
+    #[test]
+    fn pack_rejects_oversized_structures() {
+        let structure = MessageStructure::new(0x01, vec![MessageValue::Null; 16]);
+        let err = pack(MessageValue::Structure(structure)).unwrap_err();
+        assert!(matches!(err, Error::Serialization(_)));
+    }
+
+    #[test]
+    fn unpack_rejects_non_string_map_keys() {
+        let err = unpack(&[0xA1, 0x01, 0x02]).err().unwrap();
+        assert!(matches!(err, Error::Protocol(_)));
+        assert!(err.to_string().contains("Integer"));
+    }
+
+    #[test]
+    fn maps_round_trip() {
+        let mut map = HashMap::new();
+        for i in 0..20 {
+            map.insert(format!("key{}", i), MessageValue::TinyInt(i));
+        }
+        map.insert(
+            "nested".to_string(),
+            MessageValue::List(vec![MessageValue::Null]),
+        );
+        let value = MessageValue::Map(map);
+        assert!(unpack(&pack(value.clone()).unwrap()).unwrap() == value);
+    }
+
+    #[test]
+    fn unpack_rejects_malformed_input_without_panicking() {
+        assert!(unpack(&[]).is_err());
+        assert!(unpack(&[0x85, b'a', b'b']).is_err());
+        assert!(unpack(&[0x82, 0xFF, 0xFE]).is_err());
+        assert!(unpack(&[0xD0, 0xFF]).is_err());
+        assert!(unpack(&[0xCC, 0x02, 0x01]).is_err());
+        assert!(unpack(&[0xB1, 0x70]).is_err());
+        assert!(unpack(&[0xD6, 0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+        assert!(unpack(&[0xA2, 0x81, b'a', 0x01]).is_err());
+    }
+
+    #[test]
+    fn unpack_reads_sized_lengths() {
+        assert!(
+            unpack(&[0xD0, 0x02, b'h', b'i']).unwrap() == MessageValue::String("hi".to_string())
+        );
+        assert!(
+            unpack(&[0xCD, 0x00, 0x02, 0x01, 0x02]).unwrap() == MessageValue::Bytes(vec![1, 2])
+        );
+    }
+
+    #[tokio::test]
+    async fn read_message_survives_cancellation_mid_frame() {
+        let (client, mut server) = tokio::io::duplex(64);
+        let (reader, writer) = tokio::io::split(client);
+        let mut stream = PackStream::new(reader, writer);
+
+        server.write_all(&[0x00, 0x03, 0xB1, 0x70]).await.unwrap();
+        let cancelled =
+            tokio::time::timeout(Duration::from_millis(20), stream.read_message()).await;
+        assert!(cancelled.is_err());
+
+        server.write_all(&[0x01, 0x00, 0x00]).await.unwrap();
+        let message = stream.read_message().await.unwrap();
+        assert!(
+            message
+                == MessageValue::Structure(MessageStructure::new(
+                    0x70,
+                    vec![MessageValue::TinyInt(1)]
+                ))
+        );
+    }
+
+    #[test]
+    fn messages_are_split_into_chunks_of_at_most_64k() {
+        let framed = chunk(&[0xB0, 0x0F]);
+        assert_eq!(framed, [0x00, 0x02, 0xB0, 0x0F, 0x00, 0x00]);
+
+        let framed = chunk(&vec![1; MAX_CHUNK_SIZE]);
+        assert_eq!(framed.len(), MAX_CHUNK_SIZE + 4);
+        assert_eq!(framed[..2], [0xFF, 0xFF]);
+        assert_eq!(framed[MAX_CHUNK_SIZE + 2..], [0x00, 0x00]);
+
+        let framed = chunk(&vec![1; MAX_CHUNK_SIZE + 1]);
+        assert_eq!(framed.len(), MAX_CHUNK_SIZE + 7);
+        assert_eq!(framed[..2], [0xFF, 0xFF]);
+        assert_eq!(framed[MAX_CHUNK_SIZE + 2..], [0x00, 0x01, 0x01, 0x00, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn messages_around_chunk_boundaries_round_trip() {
+        let (client, server) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(client);
+        let mut client = PackStream::new(reader, writer);
+        let (reader, writer) = tokio::io::split(server);
+        let mut server = PackStream::new(reader, writer);
+
+        // A one field structure of a byte array takes 5 bytes around the data
+        // once its length needs 16 bits, and 7 from 64K on.
+        let sizes = [
+            MAX_CHUNK_SIZE - 5,
+            MAX_CHUNK_SIZE - 4,
+            MAX_CHUNK_SIZE * 2,
+            MAX_CHUNK_SIZE * 3 - 7,
+        ];
+        let messages: Vec<MessageStructure> = sizes
+            .iter()
+            .map(|&size| MessageStructure::new(0x71, vec![MessageValue::Bytes(vec![7; size])]))
+            .collect();
+        let sent = messages.clone();
+        let writer = tokio::spawn(async move {
+            for message in sent {
+                server.write_message(message).await.unwrap();
+            }
+            server.drain().await.unwrap();
+        });
+        for message in messages {
+            assert!(client.read_message().await.unwrap() == MessageValue::Structure(message));
+        }
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn read_message_skips_noop_chunks() {
+        let (client, mut server) = tokio::io::duplex(64);
+        let (reader, writer) = tokio::io::split(client);
+        let mut stream = PackStream::new(reader, writer);
+
+        server
+            .write_all(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xB0, 0x7E, 0x00, 0x00])
+            .await
+            .unwrap();
+        let message = stream.read_message().await.unwrap();
+        assert!(message == MessageValue::Structure(MessageStructure::new(0x7E, Vec::new())));
+    }
+
+    #[tokio::test]
+    async fn back_to_back_messages_reuse_the_buffer() {
+        let message = [0x00, 0x04, 0xB1, 0x71, 0x91, 0x01, 0x00, 0x00];
+        let data = message.repeat(1000);
+        let mut stream = PackStream::new(&data[..], tokio::io::sink());
+        for _ in 0..1000 {
+            stream.read_message().await.unwrap();
+        }
+        // Each message starts again at the front of the buffer, so nothing
+        // already unpacked is scanned or moved again.
+        assert!(stream.unpacker.unpackable.buffer.len() <= message.len());
+        assert!(stream.reader.is_empty());
+    }
+}
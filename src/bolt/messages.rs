@@ -0,0 +1,612 @@
+use std::collections::HashMap;
+
+use super::message::{MessageStructure, MessageValue};
+use crate::error::Error;
+
+pub const HELLO: u8 = 0x01;
+pub const GOODBYE: u8 = 0x02;
+pub const RESET: u8 = 0x0F;
+pub const RUN: u8 = 0x10;
+pub const BEGIN: u8 = 0x11;
+pub const COMMIT: u8 = 0x12;
+pub const ROLLBACK: u8 = 0x13;
+pub const DISCARD: u8 = 0x2F;
+pub const PULL: u8 = 0x3F;
+pub const ROUTE: u8 = 0x66;
+pub const LOGON: u8 = 0x6A;
+
+pub const SUCCESS: u8 = 0x70;
+pub const RECORD: u8 = 0x71;
+pub const IGNORED: u8 = 0x7E;
+pub const FAILURE: u8 = 0x7F;
+
+// PULL and DISCARD use -1 for "all remaining records" and for "the last query".
+pub const ALL: i64 = -1;
+pub const LAST_QUERY: i64 = -1;
+
+pub type Dictionary = HashMap<String, MessageValue>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hello {
+    pub extra: Dictionary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Logon {
+    pub auth: Dictionary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run {
+    pub query: String,
+    pub parameters: Dictionary,
+    pub extra: Dictionary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pull {
+    pub n: i64,
+    pub qid: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Discard {
+    pub n: i64,
+    pub qid: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Begin {
+    pub extra: Dictionary,
+}
+
+// The Bolt 4.4+ form; 4.3 sends the database name in place of `extra`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Route {
+    pub routing: Dictionary,
+    pub bookmarks: Vec<String>,
+    pub extra: Dictionary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Commit;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rollback;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reset;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Goodbye;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Success {
+    pub metadata: Dictionary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Failure {
+    pub code: String,
+    pub message: String,
+    pub metadata: Dictionary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ignored;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub fields: Vec<MessageValue>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Response {
+    Success(Success),
+    Record(Record),
+    Ignored(Ignored),
+    Failure(Failure),
+}
+
+impl Pull {
+    pub fn new(n: i64) -> Self {
+        Pull { n, qid: LAST_QUERY }
+    }
+
+    pub fn all() -> Self {
+        Pull::new(ALL)
+    }
+}
+
+impl Discard {
+    pub fn new(n: i64) -> Self {
+        Discard { n, qid: LAST_QUERY }
+    }
+
+    pub fn all() -> Self {
+        Discard::new(ALL)
+    }
+}
+
+// Checks the tag and field count and hands back the fields.
+fn fields_of(
+    structure: MessageStructure,
+    name: &str,
+    tag: u8,
+    len: usize,
+) -> Result<Vec<MessageValue>, Error> {
+    if structure.tag() != tag {
+        return Err(Error::protocol(format!(
+            "expected {} (0x{:02X}), got tag 0x{:02X}",
+            name,
+            tag,
+            structure.tag()
+        )));
+    }
+    if structure.len() != len {
+        return Err(Error::protocol(format!(
+            "{} should have {} fields, got {}",
+            name,
+            len,
+            structure.len()
+        )));
+    }
+    Ok(structure.into_fields())
+}
+
+pub(crate) fn into_dictionary(value: MessageValue, name: &str) -> Result<Dictionary, Error> {
+    match value {
+        MessageValue::Map(map) => Ok(map),
+        other => Err(Error::protocol(format!(
+            "{} should be a map, got {}",
+            name,
+            other.type_name()
+        ))),
+    }
+}
+
+pub(crate) fn into_string(value: MessageValue, name: &str) -> Result<String, Error> {
+    match value {
+        MessageValue::String(s) => Ok(s),
+        other => Err(Error::protocol(format!(
+            "{} should be a string, got {}",
+            name,
+            other.type_name()
+        ))),
+    }
+}
+
+fn single_dictionary(
+    structure: MessageStructure,
+    name: &str,
+    tag: u8,
+) -> Result<Dictionary, Error> {
+    let mut fields = fields_of(structure, name, tag, 1)?;
+    into_dictionary(fields.remove(0), name)
+}
+
+fn dictionary(entries: Vec<(&str, MessageValue)>) -> MessageValue {
+    MessageValue::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+// PULL and DISCARD carry {n, qid}; qid is left out when it means the last query.
+fn stream_control(n: i64, qid: i64) -> MessageValue {
+    let mut entries = vec![("n", MessageValue::from(n))];
+    if qid != LAST_QUERY {
+        entries.push(("qid", MessageValue::from(qid)));
+    }
+    dictionary(entries)
+}
+
+fn parse_stream_control(extra: Dictionary, name: &str) -> Result<(i64, i64), Error> {
+    let n = match extra.get("n") {
+        Some(n) => n.as_i64()?,
+        None => return Err(Error::protocol(format!("{} is missing n", name))),
+    };
+    let qid = match extra.get("qid") {
+        Some(qid) => qid.as_i64()?,
+        None => LAST_QUERY,
+    };
+    Ok((n, qid))
+}
+
+impl From<Hello> for MessageStructure {
+    fn from(hello: Hello) -> Self {
+        MessageStructure::new(HELLO, vec![MessageValue::Map(hello.extra)])
+    }
+}
+
+impl TryFrom<MessageStructure> for Hello {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let extra = single_dictionary(structure, "HELLO", HELLO)?;
+        Ok(Hello { extra })
+    }
+}
+
+impl From<Logon> for MessageStructure {
+    fn from(logon: Logon) -> Self {
+        MessageStructure::new(LOGON, vec![MessageValue::Map(logon.auth)])
+    }
+}
+
+impl TryFrom<MessageStructure> for Logon {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let auth = single_dictionary(structure, "LOGON", LOGON)?;
+        Ok(Logon { auth })
+    }
+}
+
+impl From<Run> for MessageStructure {
+    fn from(run: Run) -> Self {
+        MessageStructure::new(
+            RUN,
+            vec![
+                MessageValue::String(run.query),
+                MessageValue::Map(run.parameters),
+                MessageValue::Map(run.extra),
+            ],
+        )
+    }
+}
+
+impl TryFrom<MessageStructure> for Run {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let mut fields = fields_of(structure, "RUN", RUN, 3)?.into_iter();
+        Ok(Run {
+            query: into_string(fields.next().unwrap(), "RUN query")?,
+            parameters: into_dictionary(fields.next().unwrap(), "RUN parameters")?,
+            extra: into_dictionary(fields.next().unwrap(), "RUN extra")?,
+        })
+    }
+}
+
+impl From<Route> for MessageStructure {
+    fn from(route: Route) -> Self {
+        let bookmarks = route
+            .bookmarks
+            .into_iter()
+            .map(MessageValue::String)
+            .collect();
+        MessageStructure::new(
+            ROUTE,
+            vec![
+                MessageValue::Map(route.routing),
+                MessageValue::List(bookmarks),
+                MessageValue::Map(route.extra),
+            ],
+        )
+    }
+}
+
+impl TryFrom<MessageStructure> for Route {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let mut fields = fields_of(structure, "ROUTE", ROUTE, 3)?.into_iter();
+        let routing = into_dictionary(fields.next().unwrap(), "ROUTE routing")?;
+        let bookmarks = match fields.next().unwrap() {
+            MessageValue::List(bookmarks) => bookmarks
+                .into_iter()
+                .map(|bookmark| into_string(bookmark, "ROUTE bookmark"))
+                .collect::<Result<_, _>>()?,
+            other => {
+                return Err(Error::protocol(format!(
+                    "ROUTE bookmarks should be a list, got {}",
+                    other.type_name()
+                )))
+            }
+        };
+        Ok(Route {
+            routing,
+            bookmarks,
+            extra: into_dictionary(fields.next().unwrap(), "ROUTE extra")?,
+        })
+    }
+}
+
+impl From<Pull> for MessageStructure {
+    fn from(pull: Pull) -> Self {
+        MessageStructure::new(PULL, vec![stream_control(pull.n, pull.qid)])
+    }
+}
+
+impl TryFrom<MessageStructure> for Pull {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let extra = single_dictionary(structure, "PULL", PULL)?;
+        let (n, qid) = parse_stream_control(extra, "PULL")?;
+        Ok(Pull { n, qid })
+    }
+}
+
+impl From<Discard> for MessageStructure {
+    fn from(discard: Discard) -> Self {
+        MessageStructure::new(DISCARD, vec![stream_control(discard.n, discard.qid)])
+    }
+}
+
+impl TryFrom<MessageStructure> for Discard {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let extra = single_dictionary(structure, "DISCARD", DISCARD)?;
+        let (n, qid) = parse_stream_control(extra, "DISCARD")?;
+        Ok(Discard { n, qid })
+    }
+}
+
+impl From<Begin> for MessageStructure {
+    fn from(begin: Begin) -> Self {
+        MessageStructure::new(BEGIN, vec![MessageValue::Map(begin.extra)])
+    }
+}
+
+impl TryFrom<MessageStructure> for Begin {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let extra = single_dictionary(structure, "BEGIN", BEGIN)?;
+        Ok(Begin { extra })
+    }
+}
+
+impl From<Commit> for MessageStructure {
+    fn from(_: Commit) -> Self {
+        MessageStructure::new(COMMIT, Vec::new())
+    }
+}
+
+impl TryFrom<MessageStructure> for Commit {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        fields_of(structure, "COMMIT", COMMIT, 0)?;
+        Ok(Commit)
+    }
+}
+
+impl From<Rollback> for MessageStructure {
+    fn from(_: Rollback) -> Self {
+        MessageStructure::new(ROLLBACK, Vec::new())
+    }
+}
+
+impl TryFrom<MessageStructure> for Rollback {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        fields_of(structure, "ROLLBACK", ROLLBACK, 0)?;
+        Ok(Rollback)
+    }
+}
+
+impl From<Reset> for MessageStructure {
+    fn from(_: Reset) -> Self {
+        MessageStructure::new(RESET, Vec::new())
+    }
+}
+
+impl TryFrom<MessageStructure> for Reset {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        fields_of(structure, "RESET", RESET, 0)?;
+        Ok(Reset)
+    }
+}
+
+impl From<Goodbye> for MessageStructure {
+    fn from(_: Goodbye) -> Self {
+        MessageStructure::new(GOODBYE, Vec::new())
+    }
+}
+
+impl TryFrom<MessageStructure> for Goodbye {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        fields_of(structure, "GOODBYE", GOODBYE, 0)?;
+        Ok(Goodbye)
+    }
+}
+
+impl From<Success> for MessageStructure {
+    fn from(success: Success) -> Self {
+        MessageStructure::new(SUCCESS, vec![MessageValue::Map(success.metadata)])
+    }
+}
+
+impl TryFrom<MessageStructure> for Success {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let metadata = single_dictionary(structure, "SUCCESS", SUCCESS)?;
+        Ok(Success { metadata })
+    }
+}
+
+impl From<Failure> for MessageStructure {
+    fn from(failure: Failure) -> Self {
+        let mut metadata = failure.metadata;
+        metadata.insert("code".to_string(), MessageValue::String(failure.code));
+        metadata.insert("message".to_string(), MessageValue::String(failure.message));
+        MessageStructure::new(FAILURE, vec![MessageValue::Map(metadata)])
+    }
+}
+
+impl TryFrom<MessageStructure> for Failure {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let mut metadata = single_dictionary(structure, "FAILURE", FAILURE)?;
+        let code = match metadata.remove("code") {
+            Some(code) => into_string(code, "FAILURE code")?,
+            None => return Err(Error::protocol("FAILURE is missing code")),
+        };
+        let message = match metadata.remove("message") {
+            Some(message) => into_string(message, "FAILURE message")?,
+            None => String::new(),
+        };
+        Ok(Failure {
+            code,
+            message,
+            metadata,
+        })
+    }
+}
+
+impl From<Ignored> for MessageStructure {
+    fn from(_: Ignored) -> Self {
+        MessageStructure::new(IGNORED, Vec::new())
+    }
+}
+
+impl TryFrom<MessageStructure> for Ignored {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        fields_of(structure, "IGNORED", IGNORED, 0)?;
+        Ok(Ignored)
+    }
+}
+
+impl From<Record> for MessageStructure {
+    fn from(record: Record) -> Self {
+        MessageStructure::new(RECORD, vec![MessageValue::List(record.fields)])
+    }
+}
+
+impl TryFrom<MessageStructure> for Record {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let mut fields = fields_of(structure, "RECORD", RECORD, 1)?;
+        match fields.remove(0) {
+            MessageValue::List(fields) => Ok(Record { fields }),
+            other => Err(Error::protocol(format!(
+                "RECORD fields should be a list, got {}",
+                other.type_name()
+            ))),
+        }
+    }
+}
+
+impl From<Response> for MessageStructure {
+    fn from(response: Response) -> Self {
+        match response {
+            Response::Success(success) => success.into(),
+            Response::Record(record) => record.into(),
+            Response::Ignored(ignored) => ignored.into(),
+            Response::Failure(failure) => failure.into(),
+        }
+    }
+}
+
+impl TryFrom<MessageStructure> for Response {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        match structure.tag() {
+            SUCCESS => Ok(Response::Success(structure.try_into()?)),
+            RECORD => Ok(Response::Record(structure.try_into()?)),
+            IGNORED => Ok(Response::Ignored(structure.try_into()?)),
+            FAILURE => Ok(Response::Failure(structure.try_into()?)),
+            tag => Err(Error::protocol(format!(
+                "unknown response tag 0x{:02X}",
+                tag
+            ))),
+        }
+    }
+}
+
+impl TryFrom<MessageValue> for Response {
+    type Error = Error;
+
+    fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+        match value {
+            MessageValue::Structure(structure) => structure.try_into(),
+            other => Err(Error::protocol(format!(
+                "expected a response structure, got {}",
+                other.type_name()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bolt::message::{pack, unpack};
+
+    #[test]
+    fn run_round_trips_through_a_structure() {
+        let run = Run {
+            query: "RETURN $x".to_string(),
+            parameters: HashMap::from([("x".to_string(), MessageValue::TinyInt(1))]),
+            extra: HashMap::new(),
+        };
+        let structure = MessageStructure::from(run.clone());
+        assert_eq!(structure.tag(), RUN);
+        assert_eq!(Run::try_from(structure).unwrap(), run);
+    }
+
+    #[test]
+    fn pull_omits_qid_for_the_last_query() {
+        assert_eq!(
+            pack(MessageValue::Structure(Pull::new(1000).into())).unwrap(),
+            [0xB1, 0x3F, 0xA1, 0x81, b'n', 0xC9, 0x03, 0xE8]
+        );
+        assert_eq!(
+            pack(MessageValue::Structure(Pull::new(ALL).into())).unwrap(),
+            [0xB1, 0x3F, 0xA1, 0x81, b'n', 0xFF]
+        );
+        let pull = Pull { n: ALL, qid: 2 };
+        assert_eq!(
+            Pull::try_from(MessageStructure::from(pull.clone())).unwrap(),
+            pull
+        );
+    }
+
+    #[test]
+    fn responses_are_decoded_by_tag() {
+        let failure = [
+            0xB1, 0x7F, 0xA2, 0x84, b'c', b'o', b'd', b'e', 0x81, b'X', 0x87, b'm', b'e', b's',
+            b's', b'a', b'g', b'e', 0x82, b'n', b'o',
+        ];
+        match Response::try_from(unpack(&failure).unwrap()).unwrap() {
+            Response::Failure(failure) => {
+                assert_eq!(failure.code, "X");
+                assert_eq!(failure.message, "no");
+            }
+            other => panic!("unexpected response {:?}", other),
+        }
+        let record = unpack(&[0xB1, 0x71, 0x91, 0x01]).unwrap();
+        assert_eq!(
+            Response::try_from(record).unwrap(),
+            Response::Record(Record {
+                fields: vec![MessageValue::TinyInt(1)]
+            })
+        );
+    }
+
+    #[test]
+    fn wrong_tags_and_shapes_are_rejected() {
+        let commit = MessageStructure::from(Commit);
+        assert!(Rollback::try_from(commit).is_err());
+        let bad_success = MessageStructure::new(SUCCESS, vec![MessageValue::Null]);
+        assert!(Success::try_from(bad_success).is_err());
+        assert!(Response::try_from(MessageStructure::from(Reset)).is_err());
+    }
+}
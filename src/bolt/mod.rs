@@ -1 +1,12 @@
+#[cfg(any(test, feature = "chaos"))]
+pub mod chaos;
+pub mod connection;
+pub mod handshake;
 pub mod message;
+pub mod messages;
+pub mod pool;
+#[cfg(test)]
+pub(crate) mod stub;
+pub mod tls;
+#[cfg(test)]
+mod vectors;
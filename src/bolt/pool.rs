@@ -0,0 +1,338 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::connection::Connection;
+use crate::config::Config;
+use crate::error::Error;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolConfig {
+    pub max_size: usize,
+    // Covers waiting for a free slot as well as opening a new connection.
+    pub acquisition_timeout: Duration,
+    pub max_lifetime: Option<Duration>,
+    // Connections idle for longer are closed instead of being handed out.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            max_size: 100,
+            acquisition_timeout: Duration::from_secs(60),
+            max_lifetime: Some(Duration::from_secs(60 * 60)),
+            idle_timeout: None,
+        }
+    }
+}
+
+struct Idle {
+    connection: Connection,
+    created: Instant,
+    since: Instant,
+}
+
+struct PoolInner {
+    address: String,
+    config: Config,
+    idle: Mutex<VecDeque<Idle>>,
+    // One permit per connection in use. Idle connections hold none, and a new
+    // connection is only opened when none is idle, so the total stays in bounds.
+    permits: Arc<Semaphore>,
+    // Connections opened so far, to give each chaos transport its own seed.
+    #[cfg(any(test, feature = "chaos"))]
+    opened: std::sync::atomic::AtomicU64,
+}
+
+// Hands out connections to one server address, reusing them between sessions.
+#[derive(Clone)]
+pub struct Pool {
+    inner: Arc<PoolInner>,
+}
+
+// A connection on loan from the pool, given back when dropped.
+pub struct PooledConnection {
+    connection: Option<Connection>,
+    created: Instant,
+    pool: Arc<PoolInner>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PoolInner {
+    #[cfg(not(any(test, feature = "chaos")))]
+    fn connect_config(&self) -> Cow<'_, Config> {
+        Cow::Borrowed(&self.config)
+    }
+
+    // Numbers the next connection for its chaos transport, dropping the
+    // transport once past the faulty ones.
+    #[cfg(any(test, feature = "chaos"))]
+    fn connect_config(&self) -> Cow<'_, Config> {
+        let Some(chaos) = &self.config.chaos else {
+            return Cow::Borrowed(&self.config);
+        };
+        let n = self
+            .opened
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let mut config = self.config.clone();
+        if chaos.connections.is_some_and(|faulty| n >= faulty as u64) {
+            config.chaos = None;
+        } else if let Some(chaos) = &mut config.chaos {
+            chaos.seed = chaos.seed.wrapping_add(n);
+        }
+        Cow::Owned(config)
+    }
+
+    fn expired(&self, created: Instant) -> bool {
+        match self.config.pool.max_lifetime {
+            Some(lifetime) => created.elapsed() >= lifetime,
+            None => false,
+        }
+    }
+
+    fn stale(&self, idle: &Idle) -> bool {
+        let idle_too_long = match self.config.pool.idle_timeout {
+            Some(timeout) => idle.since.elapsed() >= timeout,
+            None => false,
+        };
+        idle_too_long || self.expired(idle.created)
+    }
+
+    // Removes connections that are past their lifetime or idle timeout. Their
+    // sockets are simply dropped; sending GOODBYE would need an await here.
+    fn reap(&self) {
+        self.idle.lock().unwrap().retain(|idle| !self.stale(idle));
+    }
+
+    // Most recently used first, so surplus connections age out at the back.
+    fn take_idle(&self) -> Option<Idle> {
+        self.reap();
+        self.idle.lock().unwrap().pop_front()
+    }
+}
+
+impl Pool {
+    // Connects to `address` using everything but the address from `config`.
+    pub fn new(address: &str, config: Config) -> Pool {
+        Pool {
+            inner: Arc::new(PoolInner {
+                address: address.to_string(),
+                permits: Arc::new(Semaphore::new(config.pool.max_size)),
+                #[cfg(any(test, feature = "chaos"))]
+                opened: Default::default(),
+                config,
+                idle: Mutex::new(VecDeque::new()),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &PoolConfig {
+        &self.inner.config.pool
+    }
+
+    pub fn idle_count(&self) -> usize {
+        self.inner.idle.lock().unwrap().len()
+    }
+
+    pub fn in_use_count(&self) -> usize {
+        self.inner.config.pool.max_size - self.inner.permits.available_permits()
+    }
+
+    pub async fn acquire(&self) -> Result<PooledConnection, Error> {
+        let timeout = self.inner.config.pool.acquisition_timeout;
+        match tokio::time::timeout(timeout, self.acquire_inner()).await {
+            Ok(result) => result,
+            Err(_) => Err(Error::PoolExhausted { timeout }),
+        }
+    }
+
+    async fn acquire_inner(&self) -> Result<PooledConnection, Error> {
+        let permit = self
+            .inner
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::PoolClosed)?;
+        while let Some(mut idle) = self.inner.take_idle() {
+            // Connections come back with unread records or an open transaction
+            // when their session was dropped mid-way; clean those up here.
+            if !idle.connection.is_idle() && cleanup(&mut idle.connection).await.is_err() {
+                continue;
+            }
+            return Ok(PooledConnection {
+                connection: Some(idle.connection),
+                created: idle.created,
+                pool: self.inner.clone(),
+                _permit: permit,
+            });
+        }
+        let inner = &self.inner;
+        let connection = Connection::connect(&inner.address, &inner.connect_config()).await?;
+        Ok(PooledConnection {
+            connection: Some(connection),
+            created: Instant::now(),
+            pool: self.inner.clone(),
+            _permit: permit,
+        })
+    }
+
+    // Drops connections that have been idle too long or reached their lifetime.
+    // This also happens on every acquisition.
+    pub fn reap(&self) {
+        self.inner.reap();
+    }
+
+    // Closes the idle connections and stops handing out new ones. Connections
+    // still in use are closed when they are given back.
+    pub async fn close(&self) {
+        self.inner.permits.close();
+        let idle: Vec<Idle> = self.inner.idle.lock().unwrap().drain(..).collect();
+        for idle in idle {
+            let _ = idle.connection.close().await;
+        }
+    }
+}
+
+async fn cleanup(connection: &mut Connection) -> Result<(), Error> {
+    if connection.is_broken() {
+        return Err(std::io::Error::other("connection is broken").into());
+    }
+    connection.finish_stream().await?;
+    if !connection.is_idle() {
+        connection.reset().await?;
+    }
+    Ok(())
+}
+
+impl PooledConnection {
+    pub fn address(&self) -> &str {
+        &self.pool.address
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.connection.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        let connection = match self.connection.take() {
+            Some(connection) => connection,
+            None => return,
+        };
+        if connection.is_broken()
+            || self.pool.expired(self.created)
+            || self.pool.permits.is_closed()
+        {
+            return;
+        }
+        self.pool.idle.lock().unwrap().push_front(Idle {
+            connection,
+            created: self.created,
+            since: Instant::now(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bolt::messages::{self, Commit};
+    use crate::bolt::stub::{stub_server, success};
+
+    fn with_pool(pool: PoolConfig) -> Config {
+        Config {
+            pool,
+            ..Config::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn reuses_released_connections() {
+        let (address, server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![success(vec![])],
+        ]])
+        .await;
+        let pool = Pool::new(&address, Config::default());
+        let first = pool.acquire().await.unwrap();
+        assert_eq!(pool.in_use_count(), 1);
+        drop(first);
+        assert_eq!((pool.in_use_count(), pool.idle_count()), (0, 1));
+
+        let mut second = pool.acquire().await.unwrap();
+        second
+            .request(messages::Begin {
+                extra: Default::default(),
+            })
+            .await
+            .unwrap();
+        second.request(Commit).await.unwrap();
+        drop(second);
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].iter().map(|r| r.tag()).collect::<Vec<_>>(),
+            [messages::HELLO, messages::BEGIN, messages::COMMIT]
+        );
+    }
+
+    #[tokio::test]
+    async fn acquisition_times_out_when_the_pool_is_exhausted() {
+        let (address, _server) = stub_server(vec![vec![vec![success(vec![])]]]).await;
+        let config = PoolConfig {
+            max_size: 1,
+            acquisition_timeout: Duration::from_millis(50),
+            ..PoolConfig::default()
+        };
+        let pool = Pool::new(&address, with_pool(config));
+        let _held = pool.acquire().await.unwrap();
+        let error = pool.acquire().await.err().unwrap();
+        assert!(matches!(error, Error::PoolExhausted { .. }));
+    }
+
+    #[tokio::test]
+    async fn expired_and_idle_connections_are_not_reused() {
+        let hello = || vec![vec![success(vec![])]];
+        let (address, server) = stub_server(vec![hello(), hello(), hello()]).await;
+        let config = PoolConfig {
+            max_lifetime: Some(Duration::ZERO),
+            ..PoolConfig::default()
+        };
+        let pool = Pool::new(&address, with_pool(config));
+        drop(pool.acquire().await.unwrap());
+        assert_eq!(pool.idle_count(), 0);
+
+        let config = PoolConfig {
+            idle_timeout: Some(Duration::from_millis(10)),
+            ..PoolConfig::default()
+        };
+        let pool = Pool::new(&address, with_pool(config));
+        drop(pool.acquire().await.unwrap());
+        assert_eq!(pool.idle_count(), 1);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        pool.reap();
+        assert_eq!(pool.idle_count(), 0);
+        drop(pool.acquire().await.unwrap());
+
+        assert_eq!(server.await.unwrap().len(), 3);
+    }
+}
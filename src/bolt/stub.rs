@@ -0,0 +1,71 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use super::message::{MessageStructure, MessageValue, PackStream};
+use super::messages::{Record, Success};
+
+pub(crate) fn success(metadata: Vec<(&str, MessageValue)>) -> MessageStructure {
+    let metadata = metadata
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+    Success { metadata }.into()
+}
+
+pub(crate) fn fields(names: &[&str]) -> MessageValue {
+    MessageValue::List(
+        names
+            .iter()
+            .map(|name| MessageValue::String(name.to_string()))
+            .collect(),
+    )
+}
+
+pub(crate) fn record(values: Vec<MessageValue>) -> MessageStructure {
+    Record { fields: values }.into()
+}
+
+pub(crate) fn tags(requests: &[MessageStructure]) -> Vec<u8> {
+    requests.iter().map(|request| request.tag()).collect()
+}
+
+// Accepts one connection per script, negotiates Bolt 4.4 and answers each
+// request with its scripted replies. Returns the requests each connection saw,
+// in the order the connections were accepted.
+pub(crate) async fn stub_server(
+    scripts: Vec<Vec<Vec<MessageStructure>>>,
+) -> (String, tokio::task::JoinHandle<Vec<Vec<MessageStructure>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = tokio::spawn(async move {
+        let mut connections = Vec::new();
+        for script in scripts {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            connections.push(tokio::spawn(async move {
+                let mut handshake = [0; 20];
+                socket.read_exact(&mut handshake).await.unwrap();
+                socket.write_all(&[0x00, 0x00, 0x04, 0x04]).await.unwrap();
+                let (reader, writer) = socket.split();
+                let mut stream = PackStream::new(reader, writer);
+                let mut requests = Vec::new();
+                for replies in script {
+                    match stream.read_message().await.unwrap() {
+                        MessageValue::Structure(request) => requests.push(request),
+                        other => panic!("expected a request, got {:?}", other),
+                    }
+                    for reply in replies {
+                        stream.write_message(reply).await.unwrap();
+                    }
+                    stream.drain().await.unwrap();
+                }
+                requests
+            }));
+        }
+        let mut requests = Vec::new();
+        for connection in connections {
+            requests.push(connection.await.unwrap());
+        }
+        requests
+    });
+    (address, server)
+}
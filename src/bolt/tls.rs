@@ -0,0 +1,274 @@
+use std::path::PathBuf;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+
+use crate::error::Error;
+
+// A plain or encrypted byte stream to the server.
+pub trait Transport: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Transport for T {}
+
+// How server certificates are checked on an encrypted connection.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trust {
+    // The Mozilla root store bundled with the driver.
+    DefaultRoots,
+    // Only certificates signed by the CAs in these PEM files.
+    CustomCertificates(Vec<PathBuf>),
+    // Any certificate, including self-signed ones (`bolt+ssc`). The connection
+    // is encrypted but the server is not authenticated.
+    TrustAll,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Encryption {
+    Off,
+    Tls(Trust),
+}
+
+fn tls_error<E: ToString>(error: E) -> Error {
+    Error::config(error.to_string())
+}
+
+// The host part of a "host:port" address, without IPv6 brackets.
+fn host_of(address: &str) -> &str {
+    let host = match address.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => host,
+        _ => address,
+    };
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+// Wraps an established TCP stream according to `encryption`.
+pub async fn wrap(
+    tcp: TcpStream,
+    address: &str,
+    encryption: &Encryption,
+) -> Result<Box<dyn Transport>, Error> {
+    match encryption {
+        Encryption::Off => Ok(Box::new(tcp)),
+        Encryption::Tls(trust) => connect_tls(tcp, host_of(address), trust).await,
+    }
+}
+
+#[cfg(not(feature = "rustls"))]
+async fn connect_tls(
+    _tcp: TcpStream,
+    _host: &str,
+    _trust: &Trust,
+) -> Result<Box<dyn Transport>, Error> {
+    Err(tls_error(
+        "encrypted connections require the `rustls` feature",
+    ))
+}
+
+#[cfg(feature = "rustls")]
+async fn connect_tls(
+    tcp: TcpStream,
+    host: &str,
+    trust: &Trust,
+) -> Result<Box<dyn Transport>, Error> {
+    use tokio_rustls::rustls::pki_types::ServerName;
+    use tokio_rustls::TlsConnector;
+
+    let config = rustls_config::client_config(trust)?;
+    let name = ServerName::try_from(host.to_string()).map_err(tls_error)?;
+    // Certificate and protocol failures carry a rustls error and won't go away
+    // when retried; other I/O failures during the handshake might.
+    let stream = TlsConnector::from(config)
+        .connect(name, tcp)
+        .await
+        .map_err(|e| {
+            let inner = e.get_ref();
+            if inner.is_some_and(|inner| inner.is::<tokio_rustls::rustls::Error>()) {
+                tls_error(e)
+            } else {
+                Error::from(e)
+            }
+        })?;
+    Ok(Box::new(stream))
+}
+
+#[cfg(feature = "rustls")]
+mod rustls_config {
+    use std::sync::Arc;
+
+    use tokio_rustls::rustls::client::danger::{
+        HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+    };
+    use tokio_rustls::rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature};
+    use tokio_rustls::rustls::pki_types::pem::PemObject;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use tokio_rustls::rustls::{
+        ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    };
+
+    use super::{tls_error, Trust};
+    use crate::error::Error;
+
+    pub(super) fn client_config(trust: &Trust) -> Result<Arc<ClientConfig>, Error> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(tls_error)?;
+        let config = match trust {
+            Trust::DefaultRoots => {
+                let roots = RootCertStore {
+                    roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+                };
+                builder.with_root_certificates(roots).with_no_client_auth()
+            }
+            Trust::CustomCertificates(paths) => {
+                let mut roots = RootCertStore::empty();
+                for path in paths {
+                    for certificate in CertificateDer::pem_file_iter(path).map_err(tls_error)? {
+                        roots
+                            .add(certificate.map_err(tls_error)?)
+                            .map_err(tls_error)?;
+                    }
+                }
+                builder.with_root_certificates(roots).with_no_client_auth()
+            }
+            Trust::TrustAll => builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate { provider }))
+                .with_no_client_auth(),
+        };
+        Ok(Arc::new(config))
+    }
+
+    // Skips certificate validation but still checks that the server holds the
+    // key of the certificate it presented.
+    #[derive(Debug)]
+    struct AcceptAnyCertificate {
+        provider: Arc<tokio_rustls::rustls::crypto::CryptoProvider>,
+    }
+
+    impl ServerCertVerifier for AcceptAnyCertificate {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+            let algorithms = &self.provider.signature_verification_algorithms;
+            verify_tls12_signature(message, cert, dss, algorithms)
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+            let algorithms = &self.provider.signature_verification_algorithms;
+            verify_tls13_signature(message, cert, dss, algorithms)
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.provider
+                .signature_verification_algorithms
+                .supported_schemes()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rustls"))]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use tokio_rustls::rustls::ServerConfig;
+    use tokio_rustls::TlsAcceptor;
+
+    use super::*;
+
+    // A TLS echo server with a fresh self-signed certificate for "localhost".
+    // Returns its address and the certificate in PEM form.
+    async fn self_signed_server() -> (String, String) {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let pem = certified.cert.pem();
+        let key =
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+        let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+        let config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![CertificateDer::from(certified.cert.der().to_vec())],
+                key,
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    if let Ok(mut stream) = acceptor.accept(tcp).await {
+                        let mut buffer = [0; 4];
+                        stream.read_exact(&mut buffer).await.unwrap();
+                        stream.write_all(&buffer).await.unwrap();
+                        stream.flush().await.unwrap();
+                    }
+                });
+            }
+        });
+        (format!("localhost:{}", port), pem)
+    }
+
+    async fn echo(address: &str, encryption: &Encryption) -> Result<[u8; 4], Error> {
+        let tcp = TcpStream::connect(address).await?;
+        let mut stream = wrap(tcp, address, encryption).await?;
+        stream.write_all(b"ping").await?;
+        stream.flush().await?;
+        let mut buffer = [0; 4];
+        stream.read_exact(&mut buffer).await?;
+        Ok(buffer)
+    }
+
+    #[test]
+    fn extracts_hosts_from_addresses() {
+        assert_eq!(host_of("localhost:7687"), "localhost");
+        assert_eq!(host_of("[::1]:7687"), "::1");
+        assert_eq!(host_of("db.example.com"), "db.example.com");
+    }
+
+    #[tokio::test]
+    async fn verifies_certificates_according_to_trust() {
+        let (address, pem) = self_signed_server().await;
+
+        let trust_all = Encryption::Tls(Trust::TrustAll);
+        assert_eq!(&echo(&address, &trust_all).await.unwrap(), b"ping");
+
+        let default_roots = Encryption::Tls(Trust::DefaultRoots);
+        let error = echo(&address, &default_roots).await.err().unwrap();
+        assert!(matches!(error, Error::Config(_)), "{:?}", error);
+        assert!(!error.is_retriable());
+
+        let path = std::env::temp_dir().join(format!("rs4neo-test-ca-{}.pem", std::process::id()));
+        std::fs::write(&path, pem).unwrap();
+        let custom = Encryption::Tls(Trust::CustomCertificates(vec![path.clone()]));
+        let result = echo(&address, &custom).await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&result.unwrap(), b"ping");
+    }
+}
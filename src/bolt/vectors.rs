@@ -0,0 +1,230 @@
+// Canonical PackStream encodings, mostly taken from the examples in the Bolt
+// PackStream specification. Every vector must pack to exactly these bytes and
+// unpack back to the same value.
+use std::collections::HashMap;
+
+use super::message::{pack, unpack, MessageStructure, MessageValue};
+
+fn repeated(marker: &[u8], byte: u8, n: usize) -> Vec<u8> {
+    let mut encoded = marker.to_vec();
+    encoded.extend(std::iter::repeat_n(byte, n));
+    encoded
+}
+
+pub(crate) fn vectors() -> Vec<(&'static str, MessageValue, Vec<u8>)> {
+    vec![
+        ("null", MessageValue::Null, vec![0xC0]),
+        ("true", MessageValue::Bool(true), vec![0xC3]),
+        ("false", MessageValue::Bool(false), vec![0xC2]),
+        (
+            "float 1.1",
+            MessageValue::Float(1.1),
+            vec![0xC1, 0x3F, 0xF1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9A],
+        ),
+        (
+            "float -1.1",
+            MessageValue::Float(-1.1),
+            vec![0xC1, 0xBF, 0xF1, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9A],
+        ),
+        ("tiny int 0", MessageValue::TinyInt(0), vec![0x00]),
+        ("tiny int 1", MessageValue::TinyInt(1), vec![0x01]),
+        ("tiny int 127", MessageValue::TinyInt(127), vec![0x7F]),
+        ("tiny int -1", MessageValue::TinyInt(-1), vec![0xFF]),
+        ("tiny int -16", MessageValue::TinyInt(-16), vec![0xF0]),
+        ("int8 -17", MessageValue::TinyInt(-17), vec![0xC8, 0xEF]),
+        ("int8 -128", MessageValue::TinyInt(-128), vec![0xC8, 0x80]),
+        (
+            "int16 1234",
+            MessageValue::SmallInt(1234),
+            vec![0xC9, 0x04, 0xD2],
+        ),
+        (
+            "int16 -129",
+            MessageValue::SmallInt(-129),
+            vec![0xC9, 0xFF, 0x7F],
+        ),
+        (
+            "int16 max",
+            MessageValue::SmallInt(i16::MAX),
+            vec![0xC9, 0x7F, 0xFF],
+        ),
+        (
+            "int32 32768",
+            MessageValue::Int(32768),
+            vec![0xCA, 0x00, 0x00, 0x80, 0x00],
+        ),
+        (
+            "int32 min",
+            MessageValue::Int(i32::MIN),
+            vec![0xCA, 0x80, 0x00, 0x00, 0x00],
+        ),
+        (
+            "int64 2147483648",
+            MessageValue::BigInt(2147483648),
+            vec![0xCB, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00],
+        ),
+        (
+            "int64 min",
+            MessageValue::BigInt(i64::MIN),
+            vec![0xCB, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        ),
+        (
+            "empty string",
+            MessageValue::String(String::new()),
+            vec![0x80],
+        ),
+        (
+            "string A",
+            MessageValue::String("A".to_string()),
+            vec![0x81, 0x41],
+        ),
+        (
+            "string 15 bytes",
+            MessageValue::String("a".repeat(15)),
+            repeated(&[0x8F], b'a', 15),
+        ),
+        (
+            "string 16 bytes",
+            MessageValue::String("a".repeat(16)),
+            repeated(&[0xD0, 0x10], b'a', 16),
+        ),
+        (
+            "string utf-8",
+            MessageValue::String("Größenmaßstäbe".to_string()),
+            vec![
+                0xD0, 0x12, 0x47, 0x72, 0xC3, 0xB6, 0xC3, 0x9F, 0x65, 0x6E, 0x6D, 0x61, 0xC3, 0x9F,
+                0x73, 0x74, 0xC3, 0xA4, 0x62, 0x65,
+            ],
+        ),
+        (
+            "string 255 bytes",
+            MessageValue::String("a".repeat(255)),
+            repeated(&[0xD0, 0xFF], b'a', 255),
+        ),
+        (
+            "string 256 bytes",
+            MessageValue::String("a".repeat(256)),
+            repeated(&[0xD1, 0x01, 0x00], b'a', 256),
+        ),
+        (
+            "string 65536 bytes",
+            MessageValue::String("a".repeat(65536)),
+            repeated(&[0xD2, 0x00, 0x01, 0x00, 0x00], b'a', 65536),
+        ),
+        (
+            "empty bytes",
+            MessageValue::Bytes(Vec::new()),
+            vec![0xCC, 0x00],
+        ),
+        (
+            "bytes 3",
+            MessageValue::Bytes(vec![1, 2, 3]),
+            vec![0xCC, 0x03, 0x01, 0x02, 0x03],
+        ),
+        (
+            "bytes 256",
+            MessageValue::Bytes(vec![0xAB; 256]),
+            repeated(&[0xCD, 0x01, 0x00], 0xAB, 256),
+        ),
+        (
+            "bytes 65536",
+            MessageValue::Bytes(vec![0xAB; 65536]),
+            repeated(&[0xCE, 0x00, 0x01, 0x00, 0x00], 0xAB, 65536),
+        ),
+        ("empty list", MessageValue::List(Vec::new()), vec![0x90]),
+        (
+            "list 1 2 3",
+            MessageValue::List(vec![
+                MessageValue::TinyInt(1),
+                MessageValue::TinyInt(2),
+                MessageValue::TinyInt(3),
+            ]),
+            vec![0x93, 0x01, 0x02, 0x03],
+        ),
+        (
+            "list 16 items",
+            MessageValue::List(vec![MessageValue::TinyInt(0); 16]),
+            repeated(&[0xD4, 0x10], 0x00, 16),
+        ),
+        (
+            "list 256 items",
+            MessageValue::List(vec![MessageValue::Null; 256]),
+            repeated(&[0xD5, 0x01, 0x00], 0xC0, 256),
+        ),
+        (
+            "list 65536 items",
+            MessageValue::List(vec![MessageValue::Bool(true); 65536]),
+            repeated(&[0xD6, 0x00, 0x01, 0x00, 0x00], 0xC3, 65536),
+        ),
+        (
+            "nested list",
+            MessageValue::List(vec![
+                MessageValue::List(Vec::new()),
+                MessageValue::String("a".to_string()),
+            ]),
+            vec![0x92, 0x90, 0x81, 0x61],
+        ),
+        ("empty map", MessageValue::Map(HashMap::new()), vec![0xA0]),
+        (
+            "map one entry",
+            MessageValue::Map(HashMap::from([("a".to_string(), MessageValue::TinyInt(1))])),
+            vec![0xA1, 0x81, 0x61, 0x01],
+        ),
+        (
+            "struct no fields",
+            MessageValue::Structure(MessageStructure::new(0x7E, Vec::new())),
+            vec![0xB0, 0x7E],
+        ),
+        (
+            "struct point2d",
+            MessageValue::Structure(MessageStructure::new(
+                0x58,
+                vec![
+                    MessageValue::SmallInt(7203),
+                    MessageValue::Float(1.0),
+                    MessageValue::Float(2.0),
+                ],
+            )),
+            vec![
+                0xB3, 0x58, 0xC9, 0x1C, 0x23, 0xC1, 0x3F, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0xC1, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+        ),
+        (
+            "nested struct",
+            MessageValue::Structure(MessageStructure::new(
+                0x71,
+                vec![MessageValue::Structure(MessageStructure::new(
+                    0x4E,
+                    vec![
+                        MessageValue::TinyInt(1),
+                        MessageValue::String("x".to_string()),
+                    ],
+                ))],
+            )),
+            vec![0xB1, 0x71, 0xB2, 0x4E, 0x01, 0x81, 0x78],
+        ),
+    ]
+}
+
+#[test]
+fn every_vector_packs_to_its_canonical_bytes() {
+    for (name, value, encoded) in vectors() {
+        assert!(
+            pack(value).unwrap() == encoded,
+            "pack mismatch for {}",
+            name
+        );
+    }
+}
+
+#[test]
+fn every_vector_unpacks_to_its_value() {
+    for (name, value, encoded) in vectors() {
+        assert!(
+            unpack(&encoded).unwrap() == value,
+            "unpack mismatch for {}",
+            name
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+// Shares bookmarks between sessions, so that each one starts from the writes
+// of the others. Sessions ask for the bookmarks before every transaction and
+// report each new one, along with those it replaces.
+pub trait BookmarkManager: Debug + Send + Sync {
+    fn bookmarks(&self) -> Vec<String>;
+    fn update_bookmarks(&self, previous: &[String], new: &[String]);
+}
+
+// Keeps the latest bookmarks in memory: a new bookmark replaces those the
+// transaction that produced it started from.
+#[derive(Debug, Default)]
+pub struct InMemoryBookmarkManager {
+    bookmarks: Mutex<HashSet<String>>,
+}
+
+impl InMemoryBookmarkManager {
+    pub fn new(initial: Vec<String>) -> InMemoryBookmarkManager {
+        InMemoryBookmarkManager {
+            bookmarks: Mutex::new(initial.into_iter().collect()),
+        }
+    }
+}
+
+impl BookmarkManager for InMemoryBookmarkManager {
+    fn bookmarks(&self) -> Vec<String> {
+        self.bookmarks.lock().unwrap().iter().cloned().collect()
+    }
+
+    fn update_bookmarks(&self, previous: &[String], new: &[String]) {
+        let mut bookmarks = self.bookmarks.lock().unwrap();
+        for bookmark in previous {
+            bookmarks.remove(bookmark);
+        }
+        bookmarks.extend(new.iter().cloned());
+    }
+}
+
+// A session's bookmarks: what it was started with, then the one left by its
+// last transaction.
+#[derive(Debug, Default)]
+pub(crate) struct Bookmarks {
+    bookmarks: Vec<String>,
+    // What the latest transaction started from, replaced in the manager by
+    // the bookmark it leaves.
+    sent: Vec<String>,
+    manager: Option<Arc<dyn BookmarkManager>>,
+}
+
+impl Bookmarks {
+    pub(crate) fn new(
+        bookmarks: Vec<String>,
+        manager: Option<Arc<dyn BookmarkManager>>,
+    ) -> Bookmarks {
+        Bookmarks {
+            bookmarks,
+            sent: Vec::new(),
+            manager,
+        }
+    }
+
+    pub(crate) fn last(&self) -> &[String] {
+        &self.bookmarks
+    }
+
+    // What the next transaction waits for: the session's bookmarks and the
+    // manager's.
+    pub(crate) fn current(&mut self) -> Vec<String> {
+        let mut bookmarks = self.bookmarks.clone();
+        if let Some(manager) = &self.manager {
+            for bookmark in manager.bookmarks() {
+                if !bookmarks.contains(&bookmark) {
+                    bookmarks.push(bookmark);
+                }
+            }
+        }
+        self.sent = bookmarks.clone();
+        bookmarks
+    }
+
+    pub(crate) fn update(&mut self, bookmark: Option<String>) {
+        let Some(bookmark) = bookmark else {
+            return;
+        };
+        self.bookmarks = vec![bookmark];
+        if let Some(manager) = &self.manager {
+            manager.update_bookmarks(&self.sent, &self.bookmarks);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(manager: &InMemoryBookmarkManager) -> Vec<String> {
+        let mut bookmarks = manager.bookmarks();
+        bookmarks.sort();
+        bookmarks
+    }
+
+    #[test]
+    fn new_bookmarks_replace_the_ones_they_follow() {
+        let manager = Arc::new(InMemoryBookmarkManager::new(vec!["a".to_string()]));
+        let mut first = Bookmarks::new(Vec::new(), Some(manager.clone()));
+        let mut second = Bookmarks::new(vec!["b".to_string()], Some(manager.clone()));
+        assert_eq!(second.current(), ["b", "a"]);
+        assert_eq!(first.current(), ["a"]);
+
+        second.update(Some("c".to_string()));
+        // Started from "a" and "b"; "b" was never the manager's.
+        assert_eq!(sorted(&manager), ["c"]);
+        second.update(None);
+        assert_eq!(second.last(), ["c"]);
+        // The first session didn't see "c", so both remain.
+        first.update(Some("d".to_string()));
+        assert_eq!(sorted(&manager), ["c", "d"]);
+        assert_eq!(first.current(), ["d", "c"]);
+        first.update(Some("e".to_string()));
+        assert_eq!(sorted(&manager), ["e"]);
+    }
+}
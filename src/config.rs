@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::auth::Auth;
+#[cfg(any(test, feature = "chaos"))]
+use crate::bolt::chaos::ChaosConfig;
+use crate::bolt::connection::USER_AGENT;
+use crate::bolt::message::DEFAULT_MAX_NESTING_DEPTH;
+use crate::bolt::pool::PoolConfig;
+use crate::bolt::tls::{Encryption, Trust};
+use crate::error::Error;
+use crate::retry::RetryConfig;
+
+pub const DEFAULT_PORT: u16 = 7687;
+pub const DEFAULT_FETCH_SIZE: i64 = 1000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub host: String,
+    pub port: u16,
+    // `neo4j://` URIs ask for a routing driver; `bolt://` ones for a direct
+    // connection to a single server.
+    pub routing: bool,
+    // Query parameters of a `neo4j://` URI, passed on to the server for routing.
+    pub routing_context: HashMap<String, String>,
+    pub auth: Auth,
+    pub encryption: Encryption,
+    pub pool: PoolConfig,
+    // Covers TCP connect, TLS, the handshake and authentication.
+    pub connect_timeout: Option<Duration>,
+    pub user_agent: String,
+    // Records requested per PULL; -1 pulls everything at once.
+    pub fetch_size: i64,
+    // Backoff for `execute_read` and `execute_write`.
+    pub retry: RetryConfig,
+    // How deeply values in a server message may nest before the connection
+    // gives up on it.
+    pub max_nesting_depth: usize,
+    // Wraps every connection in a fault-injecting transport.
+    #[cfg(any(test, feature = "chaos"))]
+    pub chaos: Option<ChaosConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            host: "localhost".to_string(),
+            port: DEFAULT_PORT,
+            routing: false,
+            routing_context: HashMap::new(),
+            auth: Auth::None,
+            encryption: Encryption::Off,
+            pool: PoolConfig::default(),
+            connect_timeout: Some(Duration::from_secs(30)),
+            user_agent: USER_AGENT.to_string(),
+            fetch_size: DEFAULT_FETCH_SIZE,
+            retry: RetryConfig::default(),
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            #[cfg(any(test, feature = "chaos"))]
+            chaos: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+fn percent_decode(text: &str) -> Result<String, Error> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| Error::config(format!("invalid percent-encoding in {:?}", text)))?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| Error::config(format!("invalid UTF-8 in {:?}", text)))
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    // Parses `scheme://[user[:password]@]host[:port][/][?key=value&...]`. The
+    // scheme is one of bolt, neo4j, and their `+s` (verified TLS) and `+ssc`
+    // (self-signed TLS) variants; a bare `host[:port]` means `bolt://`.
+    pub fn from_uri(uri: &str) -> Result<Config, Error> {
+        let mut config = Config::default();
+        let (scheme, rest) = uri.split_once("://").unwrap_or(("bolt", uri));
+        let (routing, security) = match scheme.split_once('+') {
+            Some((base, security)) => (base, Some(security)),
+            None => (scheme, None),
+        };
+        config.routing = match routing {
+            "bolt" => false,
+            "neo4j" => true,
+            _ => {
+                return Err(Error::config(format!(
+                    "unsupported URI scheme {:?}",
+                    scheme
+                )))
+            }
+        };
+        config.encryption = match security {
+            None => Encryption::Off,
+            Some("s") => Encryption::Tls(Trust::DefaultRoots),
+            Some("ssc") => Encryption::Tls(Trust::TrustAll),
+            Some(_) => {
+                return Err(Error::config(format!(
+                    "unsupported URI scheme {:?}",
+                    scheme
+                )))
+            }
+        };
+
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let (credentials, address) = match rest.rsplit_once('@') {
+            Some((credentials, address)) => (Some(credentials), address),
+            None => (None, rest),
+        };
+        if let Some(credentials) = credentials {
+            let (user, password) = credentials.split_once(':').unwrap_or((credentials, ""));
+            config.auth = Auth::basic(&percent_decode(user)?, &percent_decode(password)?);
+        }
+
+        let address = address.strip_suffix('/').unwrap_or(address);
+        if address.is_empty() || address.contains('/') {
+            return Err(Error::config(format!("invalid address in {:?}", uri)));
+        }
+        // A trailing ":port" is only one when the host isn't a bare IPv6 address.
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+                let port = port
+                    .parse()
+                    .map_err(|_| Error::config(format!("invalid port in {:?}", uri)))?;
+                (host, port)
+            }
+            _ => (address, DEFAULT_PORT),
+        };
+        config.host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        config.port = port;
+
+        if let Some(query) = query.filter(|query| !query.is_empty()) {
+            if !config.routing {
+                return Err(Error::config(format!(
+                    "routing context is not supported for {:?} URIs",
+                    scheme
+                )));
+            }
+            for pair in query.split('&') {
+                let (key, value) = pair
+                    .split_once('=')
+                    .ok_or_else(|| Error::config(format!("invalid routing context {:?}", pair)))?;
+                config
+                    .routing_context
+                    .insert(percent_decode(key)?, percent_decode(value)?);
+            }
+        }
+        Ok(config)
+    }
+
+    // "host:port", bracketing IPv6 hosts.
+    pub fn address(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl ConfigBuilder {
+    pub fn from_uri(uri: &str) -> Result<ConfigBuilder, Error> {
+        Ok(ConfigBuilder {
+            config: Config::from_uri(uri)?,
+        })
+    }
+
+    pub fn host(mut self, host: &str) -> Self {
+        self.config.host = host.to_string();
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.config.port = port;
+        self
+    }
+
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.config.auth = auth;
+        self
+    }
+
+    pub fn encryption(mut self, encryption: Encryption) -> Self {
+        self.config.encryption = encryption;
+        self
+    }
+
+    pub fn max_pool_size(mut self, size: usize) -> Self {
+        self.config.pool.max_size = size;
+        self
+    }
+
+    pub fn acquisition_timeout(mut self, timeout: Duration) -> Self {
+        self.config.pool.acquisition_timeout = timeout;
+        self
+    }
+
+    pub fn max_connection_lifetime(mut self, lifetime: Option<Duration>) -> Self {
+        self.config.pool.max_lifetime = lifetime;
+        self
+    }
+
+    pub fn idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.pool.idle_timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.connect_timeout = timeout;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.config.user_agent = user_agent.to_string();
+        self
+    }
+
+    pub fn fetch_size(mut self, fetch_size: i64) -> Self {
+        self.config.fetch_size = fetch_size;
+        self
+    }
+
+    pub fn max_nesting_depth(mut self, depth: usize) -> Self {
+        self.config.max_nesting_depth = depth;
+        self
+    }
+
+    pub fn max_transaction_retry_time(mut self, time: Duration) -> Self {
+        self.config.retry.max_retry_time = time;
+        self
+    }
+
+    pub fn build(self) -> Config {
+        self.config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_schemes_hosts_and_ports() {
+        let config = Config::from_uri("bolt://localhost").unwrap();
+        assert_eq!(config.address(), "localhost:7687");
+        assert!(!config.routing);
+        assert_eq!(config.encryption, Encryption::Off);
+
+        let config = Config::from_uri("neo4j+s://db.example.com:7688/").unwrap();
+        assert_eq!(config.address(), "db.example.com:7688");
+        assert!(config.routing);
+        assert_eq!(config.encryption, Encryption::Tls(Trust::DefaultRoots));
+
+        let config = Config::from_uri("bolt+ssc://[::1]:7000").unwrap();
+        assert_eq!((config.host.as_str(), config.port), ("::1", 7000));
+        assert_eq!(config.address(), "[::1]:7000");
+        assert_eq!(config.encryption, Encryption::Tls(Trust::TrustAll));
+
+        assert_eq!(Config::from_uri("[::1]").unwrap().address(), "[::1]:7687");
+        assert_eq!(Config::from_uri("127.0.0.1:7000").unwrap().port, 7000);
+
+        assert!(Config::from_uri("http://localhost").is_err());
+        assert!(Config::from_uri("bolt+x://localhost").is_err());
+        assert!(Config::from_uri("bolt://").is_err());
+        assert!(Config::from_uri("bolt://localhost:http").is_err());
+    }
+
+    #[test]
+    fn parses_credentials_and_routing_context() {
+        let config =
+            Config::from_uri("neo4j://neo4j:p%40ss@db:7687?region=eu&policy=fast").unwrap();
+        assert_eq!(config.auth, Auth::basic("neo4j", "p@ss"));
+        assert_eq!(config.host, "db");
+        assert_eq!(config.routing_context["region"], "eu");
+        assert_eq!(config.routing_context["policy"], "fast");
+
+        assert!(Config::from_uri("bolt://db?region=eu").is_err());
+        assert!(Config::from_uri("neo4j://db?region").is_err());
+    }
+
+    #[test]
+    fn builder_overrides_uri_settings() {
+        let config = ConfigBuilder::from_uri("bolt://db")
+            .unwrap()
+            .port(7000)
+            .auth(Auth::basic("neo4j", "secret"))
+            .max_pool_size(5)
+            .user_agent("app/1.0")
+            .build();
+        assert_eq!(config.address(), "db:7000");
+        assert_eq!(config.pool.max_size, 5);
+        assert_eq!(config.user_agent, "app/1.0");
+        assert_eq!(Config::builder().build(), Config::default());
+    }
+}
@@ -0,0 +1,190 @@
+use crate::bolt::message::MessageValue;
+use crate::bolt::messages::Dictionary;
+
+// Ready for `Session::run(&query.text, query.params)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CypherQuery {
+    pub text: String,
+    pub params: Dictionary,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Cypher {
+    clauses: Vec<String>,
+    params: Dictionary,
+    // Whether the last clause is a WHERE whose conditions are already
+    // parenthesised for joining.
+    joined: bool,
+}
+
+impl Cypher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn match_(pattern: &str) -> Self {
+        Self::new().clause("MATCH", pattern)
+    }
+
+    pub fn optional_match(pattern: &str) -> Self {
+        Self::new().clause("OPTIONAL MATCH", pattern)
+    }
+
+    pub fn create(pattern: &str) -> Self {
+        Self::new().clause("CREATE", pattern)
+    }
+
+    pub fn merge(pattern: &str) -> Self {
+        Self::new().clause("MERGE", pattern)
+    }
+
+    pub fn and_match(self, pattern: &str) -> Self {
+        self.clause("MATCH", pattern)
+    }
+
+    pub fn and_optional_match(self, pattern: &str) -> Self {
+        self.clause("OPTIONAL MATCH", pattern)
+    }
+
+    pub fn where_(self, condition: &str) -> Self {
+        self.clause("WHERE", condition)
+    }
+
+    // Joins onto the preceding WHERE if there is one, so dynamic filters can be
+    // appended without tracking whether they are the first. Each condition is
+    // parenthesised, so an OR in one doesn't swallow the others.
+    pub fn and_where(mut self, condition: &str) -> Self {
+        match self.clauses.last_mut() {
+            Some(last) if last.starts_with("WHERE ") => {
+                if !self.joined {
+                    *last = format!("WHERE ({})", &last["WHERE ".len()..]);
+                    self.joined = true;
+                }
+                last.push_str(&format!(" AND ({})", condition));
+                self
+            }
+            _ => self.where_(condition),
+        }
+    }
+
+    pub fn set(self, assignments: &str) -> Self {
+        self.clause("SET", assignments)
+    }
+
+    pub fn delete(self, items: &str) -> Self {
+        self.clause("DELETE", items)
+    }
+
+    pub fn detach_delete(self, items: &str) -> Self {
+        self.clause("DETACH DELETE", items)
+    }
+
+    pub fn with(self, items: &str) -> Self {
+        self.clause("WITH", items)
+    }
+
+    pub fn unwind(self, list: &str, alias: &str) -> Self {
+        self.clause("UNWIND", &format!("{} AS {}", list, alias))
+    }
+
+    pub fn return_(self, items: &str) -> Self {
+        self.clause("RETURN", items)
+    }
+
+    pub fn order_by(self, items: &str) -> Self {
+        self.clause("ORDER BY", items)
+    }
+
+    pub fn skip(self, n: u64) -> Self {
+        self.clause("SKIP", &n.to_string())
+    }
+
+    pub fn limit(self, n: u64) -> Self {
+        self.clause("LIMIT", &n.to_string())
+    }
+
+    // Binding the same name twice keeps the latest value.
+    pub fn param(mut self, name: &str, value: impl Into<MessageValue>) -> Self {
+        self.params.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn build(self) -> CypherQuery {
+        CypherQuery {
+            text: self.clauses.join(" "),
+            params: self.params,
+        }
+    }
+
+    fn clause(mut self, keyword: &str, body: &str) -> Self {
+        self.clauses.push(format!("{} {}", keyword, body));
+        self.joined = false;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_clauses_in_order() {
+        let query = Cypher::match_("(p:Person)")
+            .where_("p.age > $min_age")
+            .return_("p.name")
+            .order_by("p.name")
+            .limit(10)
+            .param("min_age", 18)
+            .build();
+        assert_eq!(
+            query.text,
+            "MATCH (p:Person) WHERE p.age > $min_age RETURN p.name ORDER BY p.name LIMIT 10"
+        );
+        assert_eq!(
+            query.params,
+            Dictionary::from([("min_age".to_string(), MessageValue::TinyInt(18))])
+        );
+    }
+
+    #[test]
+    fn and_where_joins_conditions() {
+        let query = Cypher::match_("(p:Person)")
+            .and_where("p.name = $name")
+            .and_where("p.age > $age")
+            .return_("p")
+            .build();
+        assert_eq!(
+            query.text,
+            "MATCH (p:Person) WHERE (p.name = $name) AND (p.age > $age) RETURN p"
+        );
+    }
+
+    #[test]
+    fn and_where_keeps_or_conditions_together() {
+        let query = Cypher::match_("(p:Person)")
+            .where_("p.admin OR p.owner")
+            .and_where("p.active")
+            .and_where("p.age > $age")
+            .with("p")
+            .where_("p.name STARTS WITH 'A'")
+            .return_("p")
+            .build();
+        assert_eq!(
+            query.text,
+            "MATCH (p:Person) WHERE (p.admin OR p.owner) AND (p.active) AND (p.age > $age) \
+             WITH p WHERE p.name STARTS WITH 'A' RETURN p"
+        );
+    }
+
+    #[test]
+    fn rebinding_a_param_replaces_it() {
+        let query = Cypher::match_("(n)")
+            .param("x", false)
+            .param("x", true)
+            .build();
+        assert_eq!(
+            query.params,
+            Dictionary::from([("x".to_string(), MessageValue::Bool(true))])
+        );
+    }
+}
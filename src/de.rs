@@ -0,0 +1,322 @@
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, EnumAccess, IntoDeserializer, VariantAccess, Visitor};
+
+use crate::bolt::message::{MessageStructure, MessageValue};
+use crate::bolt::messages::Dictionary;
+use crate::error::Error;
+use crate::graph::{self, Node, Relationship, UnboundRelationship};
+use crate::record::Record;
+use crate::spatial::{self, Point2D, Point3D};
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        Error::serialization(message.to_string())
+    }
+}
+
+pub fn from_value<T: DeserializeOwned>(value: MessageValue) -> Result<T, Error> {
+    T::deserialize(value)
+}
+
+pub fn from_map<T: DeserializeOwned>(map: Dictionary) -> Result<T, Error> {
+    T::deserialize(MessageValue::Map(map))
+}
+
+impl Record {
+    // Reads the columns into the fields of the same names.
+    pub fn to<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        let entries = self
+            .keys()
+            .iter()
+            .cloned()
+            .zip(self.values().iter().cloned());
+        T::deserialize(MapDeserializer::new(entries))
+    }
+}
+
+impl Node {
+    pub fn to<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        from_map(self.properties.clone())
+    }
+}
+
+impl Relationship {
+    pub fn to<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        from_map(self.properties.clone())
+    }
+}
+
+// Nodes and relationships read as their properties, so a column holding a
+// node can fill a struct directly. Points read as `{srid, x, y[, z]}`.
+fn structure_value(structure: MessageStructure) -> Result<MessageValue, Error> {
+    let properties = match structure.tag() {
+        graph::NODE => Node::try_from(structure)?.properties,
+        graph::RELATIONSHIP => Relationship::try_from(structure)?.properties,
+        graph::UNBOUND_RELATIONSHIP => UnboundRelationship::try_from(structure)?.properties,
+        spatial::POINT_2D => {
+            let point = Point2D::try_from(structure)?;
+            Dictionary::from([
+                ("srid".to_string(), MessageValue::BigInt(point.srid)),
+                ("x".to_string(), MessageValue::Float(point.x)),
+                ("y".to_string(), MessageValue::Float(point.y)),
+            ])
+        }
+        spatial::POINT_3D => {
+            let point = Point3D::try_from(structure)?;
+            Dictionary::from([
+                ("srid".to_string(), MessageValue::BigInt(point.srid)),
+                ("x".to_string(), MessageValue::Float(point.x)),
+                ("y".to_string(), MessageValue::Float(point.y)),
+                ("z".to_string(), MessageValue::Float(point.z)),
+            ])
+        }
+        tag => {
+            return Err(Error::serialization(format!(
+                "structures with tag 0x{:02X} can't be deserialized",
+                tag
+            )))
+        }
+    };
+    Ok(MessageValue::Map(properties))
+}
+
+impl<'de> IntoDeserializer<'de, Error> for MessageValue {
+    type Deserializer = MessageValue;
+
+    fn into_deserializer(self) -> MessageValue {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for MessageValue {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            MessageValue::String(s) => visitor.visit_string(s),
+            MessageValue::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+            MessageValue::TinyInt(i) => visitor.visit_i8(i),
+            MessageValue::SmallInt(i) => visitor.visit_i16(i),
+            MessageValue::Int(i) => visitor.visit_i32(i),
+            MessageValue::BigInt(i) => visitor.visit_i64(i),
+            MessageValue::Float(f) => visitor.visit_f64(f),
+            MessageValue::Bool(b) => visitor.visit_bool(b),
+            MessageValue::Structure(structure) => {
+                structure_value(structure)?.deserialize_any(visitor)
+            }
+            MessageValue::List(list) => {
+                let mut seq = SeqDeserializer::new(list.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            MessageValue::Map(map) => {
+                let mut map = MapDeserializer::new(map.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            MessageValue::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            MessageValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Unit variants are strings, the others single-entry maps from the
+    // variant name to its contents, as serde_json has them.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self {
+            MessageValue::String(variant) => visitor.visit_enum(Enum {
+                variant,
+                value: None,
+            }),
+            MessageValue::Map(map) if map.len() == 1 => {
+                let (variant, value) = map.into_iter().next().unwrap();
+                visitor.visit_enum(Enum {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            other => Err(Error::serialization(format!(
+                "expected the enum {}, got {}",
+                name,
+                other.type_name()
+            ))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct Enum {
+    variant: String,
+    value: Option<MessageValue>,
+}
+
+impl<'de> EnumAccess<'de> for Enum {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let variant = seed.deserialize(MessageValue::String(self.variant.clone()))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Enum {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.value {
+            None | Some(MessageValue::Null) => Ok(()),
+            Some(other) => Err(Error::serialization(format!(
+                "expected the unit variant {}, got {}",
+                self.variant,
+                other.type_name()
+            ))),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.value.unwrap_or(MessageValue::Null))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self.value.unwrap_or(MessageValue::Null), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(self.value.unwrap_or(MessageValue::Null), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Person {
+        name: String,
+        age: u8,
+        email: Option<String>,
+        tags: Vec<String>,
+    }
+
+    fn string(s: &str) -> MessageValue {
+        MessageValue::String(s.to_string())
+    }
+
+    fn alice() -> Dictionary {
+        Dictionary::from([
+            ("name".to_string(), string("Alice")),
+            ("age".to_string(), MessageValue::TinyInt(42)),
+            ("email".to_string(), MessageValue::Null),
+            (
+                "tags".to_string(),
+                MessageValue::List(vec![string("admin")]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn deserializes_records_and_nodes() {
+        let keys = vec!["name".to_string(), "age".to_string(), "tags".to_string()];
+        let values = vec![
+            string("Bob"),
+            MessageValue::SmallInt(200),
+            MessageValue::List(Vec::new()),
+        ];
+        let record = Record::new(Arc::new(keys), values);
+        let person: Person = record.to().unwrap();
+        assert_eq!((person.name.as_str(), person.age), ("Bob", 200));
+        assert_eq!(person.email, None);
+
+        let node = MessageValue::Structure(MessageStructure::new(
+            graph::NODE,
+            vec![
+                MessageValue::TinyInt(1),
+                MessageValue::List(vec![string("Person")]),
+                MessageValue::Map(alice()),
+            ],
+        ));
+        let expected = Person {
+            name: "Alice".to_string(),
+            age: 42,
+            email: None,
+            tags: vec!["admin".to_string()],
+        };
+        assert_eq!(
+            Node::try_from(node.clone())
+                .unwrap()
+                .to::<Person>()
+                .unwrap(),
+            expected
+        );
+        // A column holding a node reads as its properties.
+        let record = Record::new(Arc::new(vec!["p".to_string()]), vec![node]);
+        #[derive(Deserialize)]
+        struct Row {
+            p: Person,
+        }
+        assert_eq!(record.to::<Row>().unwrap().p, expected);
+    }
+
+    #[test]
+    fn reports_mismatched_types() {
+        let mut map = alice();
+        map.insert("age".to_string(), MessageValue::SmallInt(300));
+        let error = from_map::<Person>(map).unwrap_err();
+        assert!(matches!(error, Error::Serialization(_)), "{}", error);
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Status {
+            Active,
+            Banned { reason: String },
+        }
+        assert_eq!(
+            from_value::<Status>(string("Active")).unwrap(),
+            Status::Active
+        );
+        let banned = Dictionary::from([(
+            "Banned".to_string(),
+            MessageValue::Map(Dictionary::from([("reason".to_string(), string("spam"))])),
+        )]);
+        assert_eq!(
+            from_map::<Status>(banned).unwrap(),
+            Status::Banned {
+                reason: "spam".to_string()
+            }
+        );
+        assert!(from_value::<Status>(MessageValue::TinyInt(1)).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use crate::auth::Auth;
+use crate::bolt::pool::{Pool, PooledConnection};
+use crate::config::Config;
+use crate::error::Error;
+use crate::routing::{Router, RoutingTable};
+use crate::session::{AccessMode, Session, SessionConfig};
+
+// Entry point of the driver. A `bolt://` driver owns a pool of connections to
+// the server and lends one to each session; a `neo4j://` one keeps a pool
+// per cluster member and routes each session to a member that can serve it.
+// Clones share the pools.
+#[derive(Clone)]
+pub struct Driver {
+    config: Config,
+    connector: Connector,
+}
+
+#[derive(Clone)]
+pub(crate) enum Connector {
+    Direct(Pool),
+    Routing(Arc<Router>),
+}
+
+impl Connector {
+    pub(crate) async fn acquire(
+        &self,
+        mode: AccessMode,
+        database: Option<&str>,
+        bookmarks: &[String],
+    ) -> Result<PooledConnection, Error> {
+        match self {
+            Connector::Direct(pool) => pool.acquire().await,
+            Connector::Routing(router) => router.acquire(mode, database, bookmarks).await,
+        }
+    }
+
+    // What sessions that name no database are pinned to: the resolved home
+    // database of a cluster, or None to leave it to a single server.
+    pub(crate) async fn home_database(
+        &self,
+        bookmarks: &[String],
+    ) -> Result<Option<String>, Error> {
+        match self {
+            Connector::Direct(_) => Ok(None),
+            Connector::Routing(router) => router.home_database(bookmarks).await,
+        }
+    }
+
+    pub(crate) fn is_routing(&self) -> bool {
+        matches!(self, Connector::Routing(_))
+    }
+
+    // Keeps requests away from a member that just failed.
+    pub(crate) fn on_error(&self, address: &str, error: &Error) {
+        let Connector::Routing(router) = self else {
+            return;
+        };
+        match error {
+            Error::Connection(_) | Error::Routing(_) => router.forget(address),
+            _ => match error.code() {
+                Some("Neo.ClientError.Cluster.NotALeader")
+                | Some("Neo.ClientError.General.ForbiddenOnReadOnlyDatabase") => {
+                    router.forget_writer(address)
+                }
+                _ => {}
+            },
+        }
+    }
+
+    async fn close(&self) {
+        match self {
+            Connector::Direct(pool) => pool.close().await,
+            Connector::Routing(router) => router.close().await,
+        }
+    }
+}
+
+impl Driver {
+    // `auth` replaces any credentials in the URI unless it is `Auth::None`.
+    pub fn new(uri: &str, auth: Auth) -> Result<Driver, Error> {
+        let mut config = Config::from_uri(uri)?;
+        if auth != Auth::None {
+            config.auth = auth;
+        }
+        Ok(Driver::with_config(config))
+    }
+
+    pub fn with_config(config: Config) -> Driver {
+        let connector = if config.routing {
+            Connector::Routing(Arc::new(Router::new(config.clone())))
+        } else {
+            Connector::Direct(Pool::new(&config.address(), config.clone()))
+        };
+        Driver { config, connector }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // The pool of a direct driver; routing drivers have one per member.
+    pub fn pool(&self) -> Option<&Pool> {
+        match &self.connector {
+            Connector::Direct(pool) => Some(pool),
+            Connector::Routing(_) => None,
+        }
+    }
+
+    // The last routing table fetched for `database`, None for the home
+    // database. Always None for a direct driver.
+    pub fn routing_table(&self, database: Option<&str>) -> Option<RoutingTable> {
+        match &self.connector {
+            Connector::Direct(_) => None,
+            Connector::Routing(router) => router.table(database),
+        }
+    }
+
+    pub async fn session(&self) -> Result<Session, Error> {
+        self.session_with(SessionConfig::default()).await
+    }
+
+    pub async fn session_with(&self, config: SessionConfig) -> Result<Session, Error> {
+        Session::new(self.connector.clone(), config, &self.config).await
+    }
+
+    pub async fn close(&self) {
+        self.connector.close().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drivers_can_be_shared_between_tasks() {
+        fn shareable<T: Clone + Send + Sync + 'static>() {}
+        shareable::<Driver>();
+    }
+}
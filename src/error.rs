@@ -0,0 +1,157 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::bolt::messages::Failure;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    // The server sent something that isn't valid Bolt, or not what the
+    // conversation called for.
+    Protocol(String),
+    // A message nested deeper than the configured limit.
+    NestingTooDeep { max_depth: usize },
+    // A FAILURE response.
+    Server { code: String, message: String },
+    // The socket, TLS session or connection setup failed.
+    Connection(std::io::Error),
+    // A value that can't be packed, or converted to the type asked for.
+    Serialization(String),
+    // A record has no column of this name.
+    MissingColumn(String),
+    Timeout(String),
+    // No pooled connection became free within the acquisition timeout.
+    PoolExhausted { timeout: Duration },
+    PoolClosed,
+    // No cluster member could serve the request, or none could be asked
+    // which ones can.
+    Routing(String),
+    // An unusable URI or configuration.
+    Config(String),
+}
+
+// Transient errors may succeed when retried; a cluster member that can no
+// longer serve writes is worth retrying against a fresh routing table. A
+// terminated transaction is transient in name only: someone asked for it.
+fn is_retriable_code(code: &str) -> bool {
+    match code {
+        "Neo.TransientError.Transaction.Terminated" => false,
+        "Neo.ClientError.Cluster.NotALeader"
+        | "Neo.ClientError.General.ForbiddenOnReadOnlyDatabase" => true,
+        code => code.starts_with("Neo.TransientError."),
+    }
+}
+
+impl Error {
+    pub(crate) fn protocol(message: impl Into<String>) -> Error {
+        Error::Protocol(message.into())
+    }
+
+    pub(crate) fn serialization(message: impl Into<String>) -> Error {
+        Error::Serialization(message.into())
+    }
+
+    pub(crate) fn config(message: impl Into<String>) -> Error {
+        Error::Config(message.into())
+    }
+
+    // The Neo4j status code of a server error.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Error::Server { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    // Whether running the same work again, on a new connection if need be,
+    // may succeed. Connection failures count, timeouts waiting on the server
+    // and the pool don't: they are more likely to time out again.
+    pub fn is_retriable(&self) -> bool {
+        match self {
+            Error::Server { code, .. } => is_retriable_code(code),
+            Error::Connection(_) | Error::Routing(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Protocol(message) => write!(f, "protocol error: {}", message),
+            Error::NestingTooDeep { max_depth } => write!(
+                f,
+                "protocol error: message exceeds maximum nesting depth of {}",
+                max_depth
+            ),
+            Error::Server { code, message } => write!(f, "{}: {}", code, message),
+            Error::Connection(e) => write!(f, "connection error: {}", e),
+            Error::Serialization(message) => write!(f, "serialization error: {}", message),
+            Error::MissingColumn(key) => write!(f, "the record has no column {:?}", key),
+            Error::Timeout(message) => f.write_str(message),
+            Error::PoolExhausted { timeout } => write!(
+                f,
+                "no pooled connection became available within {:?}",
+                timeout
+            ),
+            Error::PoolClosed => f.write_str("connection pool is closed"),
+            Error::Routing(message) => write!(f, "routing error: {}", message),
+            Error::Config(message) => write!(f, "invalid configuration: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Connection(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Connection(e)
+    }
+}
+
+impl From<Failure> for Error {
+    fn from(failure: Failure) -> Self {
+        Error::Server {
+            code: failure.code,
+            message: failure.message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(code: &str) -> Error {
+        Error::Server {
+            code: code.to_string(),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn classifies_retriable_errors() {
+        assert!(server("Neo.TransientError.General.DatabaseUnavailable").is_retriable());
+        assert!(server("Neo.ClientError.Cluster.NotALeader").is_retriable());
+        assert!(server("Neo.TransientError.Transaction.LockClientStopped").is_retriable());
+        assert!(!server("Neo.TransientError.Transaction.Terminated").is_retriable());
+        assert!(!server("Neo.ClientError.Statement.SyntaxError").is_retriable());
+        assert!(!server("Neo.DatabaseError.General.UnknownError").is_retriable());
+
+        let reset = std::io::Error::from(std::io::ErrorKind::ConnectionReset);
+        assert!(Error::from(reset).is_retriable());
+        assert!(!Error::protocol("bad marker").is_retriable());
+        assert_eq!(
+            server("Neo.ClientError.Statement.SyntaxError").code(),
+            Some("Neo.ClientError.Statement.SyntaxError")
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use crate::bolt::message::{MessageStructure, MessageValue};
+use crate::bolt::messages::{into_dictionary, into_string, Dictionary};
+use crate::error::Error;
+
+pub const NODE: u8 = 0x4E;
+pub const RELATIONSHIP: u8 = 0x52;
+pub const UNBOUND_RELATIONSHIP: u8 = 0x72;
+pub const PATH: u8 = 0x50;
+
+// Element ids arrived with Bolt 5. Older servers only send the numeric id, which
+// then doubles as the element id.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub id: i64,
+    pub element_id: String,
+    pub labels: Vec<String>,
+    pub properties: Dictionary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Relationship {
+    pub id: i64,
+    pub element_id: String,
+    pub start_node_id: i64,
+    pub start_node_element_id: String,
+    pub end_node_id: i64,
+    pub end_node_element_id: String,
+    pub rel_type: String,
+    pub properties: Dictionary,
+}
+
+// A relationship inside a path, where its end points are implied by position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnboundRelationship {
+    pub id: i64,
+    pub element_id: String,
+    pub rel_type: String,
+    pub properties: Dictionary,
+}
+
+// Nodes and relationships in traversal order: relationship `i` joins node `i`
+// to node `i + 1`, and is bound in whichever direction it points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    pub nodes: Vec<Node>,
+    pub relationships: Vec<Relationship>,
+}
+
+impl Path {
+    // None only for a path built by hand without nodes; decoded paths always
+    // have at least one.
+    pub fn start(&self) -> Option<&Node> {
+        self.nodes.first()
+    }
+
+    pub fn end(&self) -> Option<&Node> {
+        self.nodes.last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.relationships.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relationships.is_empty()
+    }
+}
+
+// Checks the tag and that the field count is one of `lens`.
+fn fields_of(
+    structure: MessageStructure,
+    name: &str,
+    tag: u8,
+    lens: &[usize],
+) -> Result<std::vec::IntoIter<MessageValue>, Error> {
+    if structure.tag() != tag {
+        return Err(Error::protocol(format!(
+            "expected {} (0x{:02X}), got tag 0x{:02X}",
+            name,
+            tag,
+            structure.tag()
+        )));
+    }
+    if !lens.contains(&structure.len()) {
+        return Err(Error::protocol(format!(
+            "{} should have {:?} fields, got {}",
+            name,
+            lens,
+            structure.len()
+        )));
+    }
+    Ok(structure.into_fields().into_iter())
+}
+
+fn into_list(value: MessageValue, name: &str) -> Result<Vec<MessageValue>, Error> {
+    match value {
+        MessageValue::List(list) => Ok(list),
+        other => Err(Error::protocol(format!(
+            "{} should be a list, got {}",
+            name,
+            other.type_name()
+        ))),
+    }
+}
+
+fn into_structure(value: MessageValue, name: &str) -> Result<MessageStructure, Error> {
+    match value {
+        MessageValue::Structure(structure) => Ok(structure),
+        other => Err(Error::protocol(format!(
+            "{} should be a structure, got {}",
+            name,
+            other.type_name()
+        ))),
+    }
+}
+
+// The element id field, or the numeric id on servers before Bolt 5.
+fn element_id(field: Option<MessageValue>, id: i64, name: &str) -> Result<String, Error> {
+    match field {
+        Some(value) => into_string(value, name),
+        None => Ok(id.to_string()),
+    }
+}
+
+impl TryFrom<MessageStructure> for Node {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let mut fields = fields_of(structure, "Node", NODE, &[3, 4])?;
+        let id = fields.next().unwrap().as_i64()?;
+        let labels = into_list(fields.next().unwrap(), "Node labels")?
+            .into_iter()
+            .map(|label| into_string(label, "Node label"))
+            .collect::<Result<_, _>>()?;
+        let properties = into_dictionary(fields.next().unwrap(), "Node properties")?;
+        Ok(Node {
+            id,
+            element_id: element_id(fields.next(), id, "Node element id")?,
+            labels,
+            properties,
+        })
+    }
+}
+
+impl TryFrom<MessageStructure> for Relationship {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let mut fields = fields_of(structure, "Relationship", RELATIONSHIP, &[5, 8])?;
+        let id = fields.next().unwrap().as_i64()?;
+        let start_node_id = fields.next().unwrap().as_i64()?;
+        let end_node_id = fields.next().unwrap().as_i64()?;
+        let rel_type = into_string(fields.next().unwrap(), "Relationship type")?;
+        let properties = into_dictionary(fields.next().unwrap(), "Relationship properties")?;
+        Ok(Relationship {
+            id,
+            element_id: element_id(fields.next(), id, "Relationship element id")?,
+            start_node_id,
+            start_node_element_id: element_id(fields.next(), start_node_id, "start element id")?,
+            end_node_id,
+            end_node_element_id: element_id(fields.next(), end_node_id, "end element id")?,
+            rel_type,
+            properties,
+        })
+    }
+}
+
+impl TryFrom<MessageStructure> for UnboundRelationship {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let name = "UnboundRelationship";
+        let mut fields = fields_of(structure, name, UNBOUND_RELATIONSHIP, &[3, 4])?;
+        let id = fields.next().unwrap().as_i64()?;
+        let rel_type = into_string(fields.next().unwrap(), "UnboundRelationship type")?;
+        let properties = into_dictionary(fields.next().unwrap(), "UnboundRelationship properties")?;
+        Ok(UnboundRelationship {
+            id,
+            element_id: element_id(fields.next(), id, "UnboundRelationship element id")?,
+            rel_type,
+            properties,
+        })
+    }
+}
+
+impl UnboundRelationship {
+    pub fn bind(self, start: &Node, end: &Node) -> Relationship {
+        Relationship {
+            id: self.id,
+            element_id: self.element_id,
+            start_node_id: start.id,
+            start_node_element_id: start.element_id.clone(),
+            end_node_id: end.id,
+            end_node_element_id: end.element_id.clone(),
+            rel_type: self.rel_type,
+            properties: self.properties,
+        }
+    }
+}
+
+// A path arrives as its distinct nodes and relationships plus a list of
+// indices walking it: alternately a relationship (1-based, negative when
+// traversed against its direction) and the node it leads to.
+impl TryFrom<MessageStructure> for Path {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let mut fields = fields_of(structure, "Path", PATH, &[3])?;
+        let nodes = into_list(fields.next().unwrap(), "Path nodes")?
+            .into_iter()
+            .map(|node| Node::try_from(into_structure(node, "Path node")?))
+            .collect::<Result<Vec<_>, _>>()?;
+        let relationships = into_list(fields.next().unwrap(), "Path relationships")?
+            .into_iter()
+            .map(|rel| UnboundRelationship::try_from(into_structure(rel, "Path relationship")?))
+            .collect::<Result<Vec<_>, _>>()?;
+        let indices = into_list(fields.next().unwrap(), "Path indices")?
+            .iter()
+            .map(MessageValue::as_i64)
+            .collect::<Result<Vec<_>, _>>()?;
+        if nodes.is_empty() || indices.len() % 2 != 0 {
+            return Err(Error::protocol(
+                "Path has no nodes or an odd number of indices".to_string(),
+            ));
+        }
+
+        let out_of_range = || Error::protocol("Path index out of range");
+        let mut path = Path {
+            nodes: vec![nodes[0].clone()],
+            relationships: Vec::with_capacity(indices.len() / 2),
+        };
+        let mut previous = &nodes[0];
+        for step in indices.chunks(2) {
+            // Index 0 wraps around and is rejected as out of range.
+            let rel = relationships
+                .get((step[0].unsigned_abs() as usize).wrapping_sub(1))
+                .ok_or_else(out_of_range)?;
+            let next = nodes
+                .get(usize::try_from(step[1]).map_err(|_| out_of_range())?)
+                .ok_or_else(out_of_range)?;
+            let rel = if step[0] > 0 {
+                rel.clone().bind(previous, next)
+            } else {
+                rel.clone().bind(next, previous)
+            };
+            path.relationships.push(rel);
+            path.nodes.push(next.clone());
+            previous = next;
+        }
+        Ok(path)
+    }
+}
+
+macro_rules! try_from_value {
+    ($($kind:ident),*) => {
+        $(impl TryFrom<MessageValue> for $kind {
+            type Error = Error;
+
+            fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+                $kind::try_from(into_structure(value, stringify!($kind))?)
+            }
+        })*
+    };
+}
+
+try_from_value!(Node, Relationship, UnboundRelationship, Path);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> MessageValue {
+        MessageValue::String(s.to_string())
+    }
+
+    fn node(id: i8, label: &str) -> MessageValue {
+        MessageValue::Structure(MessageStructure::new(
+            NODE,
+            vec![
+                MessageValue::TinyInt(id),
+                MessageValue::List(vec![string(label)]),
+                MessageValue::Map(Dictionary::new()),
+                string(&format!("4:db:{}", id)),
+            ],
+        ))
+    }
+
+    fn unbound(id: i8, rel_type: &str) -> MessageValue {
+        MessageValue::Structure(MessageStructure::new(
+            UNBOUND_RELATIONSHIP,
+            vec![
+                MessageValue::TinyInt(id),
+                string(rel_type),
+                MessageValue::Map(Dictionary::new()),
+            ],
+        ))
+    }
+
+    #[test]
+    fn decodes_nodes_and_relationships() {
+        let node = Node::try_from(node(1, "Person")).unwrap();
+        assert_eq!(node.labels, ["Person"]);
+        assert_eq!(node.element_id, "4:db:1");
+
+        let properties = Dictionary::from([("since".to_string(), MessageValue::Int(2020))]);
+        let rel = MessageValue::Structure(MessageStructure::new(
+            RELATIONSHIP,
+            vec![
+                MessageValue::TinyInt(7),
+                MessageValue::TinyInt(1),
+                MessageValue::TinyInt(2),
+                string("KNOWS"),
+                MessageValue::Map(properties.clone()),
+            ],
+        ));
+        let rel = Relationship::try_from(rel).unwrap();
+        assert_eq!((rel.start_node_id, rel.end_node_id), (1, 2));
+        assert_eq!(
+            (rel.element_id.as_str(), rel.rel_type.as_str()),
+            ("7", "KNOWS")
+        );
+        assert_eq!(rel.properties, properties);
+
+        assert!(Node::try_from(unbound(1, "KNOWS")).is_err());
+        assert!(Node::try_from(MessageValue::Null).is_err());
+    }
+
+    #[test]
+    fn decodes_paths_in_traversal_order() {
+        // (a)-[:KNOWS]->(b)<-[:LIKES]-(c)
+        let path = MessageValue::Structure(MessageStructure::new(
+            PATH,
+            vec![
+                MessageValue::List(vec![node(1, "A"), node(2, "B"), node(3, "C")]),
+                MessageValue::List(vec![unbound(10, "KNOWS"), unbound(11, "LIKES")]),
+                MessageValue::List(
+                    [1, 1, -2, 2]
+                        .into_iter()
+                        .map(MessageValue::TinyInt)
+                        .collect(),
+                ),
+            ],
+        ));
+        let path = Path::try_from(path).unwrap();
+        assert_eq!(path.len(), 2);
+        let ids: Vec<i64> = path.nodes.iter().map(|node| node.id).collect();
+        assert_eq!(ids, [1, 2, 3]);
+        let ends: Vec<(i64, i64)> = path
+            .relationships
+            .iter()
+            .map(|rel| (rel.start_node_id, rel.end_node_id))
+            .collect();
+        assert_eq!(ends, [(1, 2), (3, 2)]);
+        assert_eq!(path.start().unwrap().id, 1);
+        assert_eq!(path.end().unwrap().id, 3);
+        let empty = Path {
+            nodes: Vec::new(),
+            relationships: Vec::new(),
+        };
+        assert!(empty.start().is_none() && empty.end().is_none());
+
+        let bad = MessageValue::Structure(MessageStructure::new(
+            PATH,
+            vec![
+                MessageValue::List(vec![node(1, "A")]),
+                MessageValue::List(vec![]),
+                MessageValue::List(vec![MessageValue::TinyInt(0), MessageValue::TinyInt(0)]),
+            ],
+        ));
+        assert!(Path::try_from(bad).is_err());
+    }
+}
@@ -1,4 +1,36 @@
-mod bolt;
+pub mod auth;
+pub mod bolt;
+pub mod bookmarks;
+pub mod config;
+pub mod cypher;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod driver;
+pub mod error;
+pub mod graph;
+pub mod record;
+pub mod retry;
+pub mod routing;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod session;
+pub mod spatial;
+pub mod stream;
+pub mod summary;
+
+pub use auth::Auth;
+pub use bookmarks::{BookmarkManager, InMemoryBookmarkManager};
+pub use config::{Config, ConfigBuilder};
+pub use driver::Driver;
+pub use error::{Error, Result};
+pub use graph::{Node, Path, Relationship, UnboundRelationship};
+pub use record::Record;
+pub use retry::RetryConfig;
+pub use routing::RoutingTable;
+pub use session::{AccessMode, Session, SessionConfig, Transaction, TransactionConfig};
+pub use spatial::{Point2D, Point3D};
+pub use stream::RecordStream;
+pub use summary::ResultSummary;
 
 #[cfg(test)]
 mod tests {
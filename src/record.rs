@@ -0,0 +1,85 @@
+use std::sync::Arc;
+
+use crate::bolt::message::MessageValue;
+use crate::error::Error;
+
+// One row of a result. The column names are shared by every record of a stream.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    keys: Arc<Vec<String>>,
+    values: Vec<MessageValue>,
+}
+
+impl Record {
+    pub(crate) fn new(keys: Arc<Vec<String>>, values: Vec<MessageValue>) -> Record {
+        Record { keys, values }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub fn values(&self) -> &[MessageValue] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<MessageValue> {
+        self.values
+    }
+
+    pub fn get(&self, key: &str) -> Option<&MessageValue> {
+        let index = self.keys.iter().position(|k| k == key)?;
+        self.values.get(index)
+    }
+
+    // The column converted to `T`. A missing column is `Error::MissingColumn`,
+    // a value of the wrong type `Error::Serialization`.
+    pub fn get_as<T: TryFrom<MessageValue, Error = Error>>(&self, key: &str) -> Result<T, Error> {
+        let value = self
+            .get(key)
+            .ok_or_else(|| Error::MissingColumn(key.to_string()))?;
+        T::try_from(value.clone())
+    }
+
+    // Like `get_as`, with null read as None.
+    pub fn get_opt<T: TryFrom<MessageValue, Error = Error>>(
+        &self,
+        key: &str,
+    ) -> Result<Option<T>, Error> {
+        self.get_as(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_getters_tell_null_from_missing() {
+        let keys = Arc::new(vec!["name".to_string(), "email".to_string()]);
+        let values = vec![MessageValue::from("Alice"), MessageValue::Null];
+        let record = Record::new(keys, values);
+        assert_eq!(record.get_as::<String>("name").unwrap(), "Alice");
+        assert_eq!(record.get_opt::<String>("email").unwrap(), None);
+        assert_eq!(
+            record.get_opt::<String>("name").unwrap().as_deref(),
+            Some("Alice")
+        );
+        assert!(matches!(
+            record.get_opt::<String>("age"),
+            Err(Error::MissingColumn(_))
+        ));
+        assert!(matches!(
+            record.get_as::<i64>("name"),
+            Err(Error::Serialization(_))
+        ));
+    }
+}
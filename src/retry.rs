@@ -0,0 +1,62 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+// How managed transactions back off between attempts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryConfig {
+    // No new attempt starts once this long has passed since the first one.
+    pub max_retry_time: Duration,
+    pub initial_delay: Duration,
+    pub multiplier: f64,
+    // Each delay is lengthened or shortened at random by up to this fraction,
+    // so clients that failed together don't all retry together.
+    pub jitter: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_retry_time: Duration::from_secs(30),
+            initial_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+// A number in [0, 1). Every RandomState is keyed afresh, which is random enough
+// for jitter without depending on rand.
+fn random() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl RetryConfig {
+    // The delay before retry number `retry`, counting from 0.
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        let base = self.initial_delay.as_secs_f64() * self.multiplier.powf(retry as f64);
+        let base = base.min(self.max_retry_time.as_secs_f64());
+        let jitter = base * self.jitter * (2.0 * random() - 1.0);
+        Duration::from_secs_f64((base + jitter).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_grow_within_the_jitter() {
+        let config = RetryConfig::default();
+        for (retry, base) in [(0, 1.0), (1, 2.0), (3, 8.0), (10, 30.0)] {
+            let delay = config.delay(retry).as_secs_f64();
+            assert!(
+                (base * 0.8..=base * 1.2).contains(&delay),
+                "retry {} waited {}s",
+                retry,
+                delay
+            );
+        }
+    }
+}
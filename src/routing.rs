@@ -0,0 +1,603 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::bolt::connection::Connection;
+use crate::bolt::handshake::BoltVersion;
+use crate::bolt::message::{MessageStructure, MessageValue};
+use crate::bolt::messages::{into_dictionary, into_string, Dictionary, Route, Run, ALL, ROUTE};
+use crate::bolt::pool::{Pool, PooledConnection};
+use crate::config::Config;
+use crate::error::Error;
+use crate::session::{field_names, AccessMode};
+
+// Which cluster members serve a database, as reported by a router.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoutingTable {
+    // The database the table is for; None only when the server didn't say
+    // and none was asked for.
+    pub database: Option<String>,
+    pub routers: Vec<String>,
+    pub readers: Vec<String>,
+    pub writers: Vec<String>,
+    pub expires: Instant,
+}
+
+fn string_list(value: MessageValue, name: &str) -> Result<Vec<String>, Error> {
+    match value {
+        MessageValue::List(list) => list
+            .into_iter()
+            .map(|item| into_string(item, name))
+            .collect(),
+        other => Err(Error::protocol(format!(
+            "{} should be a list, got {}",
+            name,
+            other.type_name()
+        ))),
+    }
+}
+
+impl RoutingTable {
+    // Parses `{ttl, servers: [{addresses, role}], db}`, the `rt` entry of a
+    // ROUTE SUCCESS and the columns of the routing procedures alike.
+    pub(crate) fn parse(mut rt: Dictionary, database: Option<&str>) -> Result<Self, Error> {
+        let ttl = match rt.get("ttl") {
+            Some(ttl) => ttl.as_i64()?,
+            None => return Err(Error::protocol("routing table is missing ttl")),
+        };
+        let servers = match rt.remove("servers") {
+            Some(servers) => servers,
+            None => return Err(Error::protocol("routing table is missing servers")),
+        };
+        let servers = match servers {
+            MessageValue::List(servers) => servers,
+            other => {
+                return Err(Error::protocol(format!(
+                    "routing table servers should be a list, got {}",
+                    other.type_name()
+                )))
+            }
+        };
+        let mut table = RoutingTable {
+            database: match rt.remove("db") {
+                Some(MessageValue::String(db)) => Some(db),
+                _ => database.map(str::to_string),
+            },
+            routers: Vec::new(),
+            readers: Vec::new(),
+            writers: Vec::new(),
+            expires: Instant::now() + Duration::from_secs(ttl.max(0) as u64),
+        };
+        for server in servers {
+            let mut server = into_dictionary(server, "routing table server")?;
+            let role = match server.remove("role") {
+                Some(role) => into_string(role, "server role")?,
+                None => return Err(Error::protocol("routing table server is missing role")),
+            };
+            let addresses = match server.remove("addresses") {
+                Some(addresses) => string_list(addresses, "server addresses")?,
+                None => Vec::new(),
+            };
+            match role.as_str() {
+                "ROUTE" => table.routers.extend(addresses),
+                "READ" => table.readers.extend(addresses),
+                "WRITE" => table.writers.extend(addresses),
+                _ => {}
+            }
+        }
+        Ok(table)
+    }
+
+    pub fn servers(&self, mode: AccessMode) -> &[String] {
+        match mode {
+            AccessMode::Read => &self.readers,
+            AccessMode::Write => &self.writers,
+        }
+    }
+
+    // Still valid, and able to serve `mode` and to be refreshed.
+    fn is_fresh(&self, mode: AccessMode) -> bool {
+        Instant::now() < self.expires && !self.routers.is_empty() && !self.servers(mode).is_empty()
+    }
+
+    fn forget(&mut self, address: &str) {
+        self.routers.retain(|router| router != address);
+        self.readers.retain(|reader| reader != address);
+        self.writers.retain(|writer| writer != address);
+    }
+}
+
+// The URI's query parameters plus the address it names, sent in HELLO and
+// with every routing table request.
+pub(crate) fn routing_context(config: &Config) -> Dictionary {
+    let mut context: Dictionary = config
+        .routing_context
+        .iter()
+        .map(|(key, value)| (key.clone(), MessageValue::String(value.clone())))
+        .collect();
+    context.insert(
+        "address".to_string(),
+        MessageValue::String(config.address()),
+    );
+    context
+}
+
+fn role(mode: AccessMode) -> &'static str {
+    match mode {
+        AccessMode::Read => "readers",
+        AccessMode::Write => "writers",
+    }
+}
+
+// Connections for a `neo4j://` driver: one pool per cluster member, and a
+// routing table per database deciding which member each request goes to.
+pub(crate) struct Router {
+    config: Config,
+    // The address in the URI, asked for a routing table when none of the
+    // known routers answers.
+    seed: String,
+    pools: Mutex<HashMap<String, Pool>>,
+    tables: Mutex<HashMap<Option<String>, RoutingTable>>,
+    // The home database once resolved, None inside for servers before Bolt
+    // 4.4, which don't name it. The driver talks as a single user, so one
+    // answer holds for all its sessions.
+    home: Mutex<Option<Option<String>>>,
+    // Held while fetching a routing table, so concurrent sessions wait for
+    // one refresh instead of each starting their own.
+    refresh: tokio::sync::Mutex<()>,
+    next: AtomicUsize,
+}
+
+impl Router {
+    pub(crate) fn new(config: Config) -> Router {
+        Router {
+            seed: config.address(),
+            config,
+            pools: Mutex::new(HashMap::new()),
+            tables: Mutex::new(HashMap::new()),
+            home: Mutex::new(None),
+            refresh: tokio::sync::Mutex::new(()),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    // Tables for the home database are kept under its name once known.
+    fn key(&self, database: Option<&str>) -> Option<String> {
+        match database {
+            Some(database) => Some(database.to_string()),
+            None => self.home.lock().unwrap().clone().flatten(),
+        }
+    }
+
+    pub(crate) fn table(&self, database: Option<&str>) -> Option<RoutingTable> {
+        let key = self.key(database);
+        self.tables.lock().unwrap().get(&key).cloned()
+    }
+
+    // Resolves the home database from a routing table fetched without naming
+    // one, which Bolt 4.4 servers label with the database it is for.
+    pub(crate) async fn home_database(
+        &self,
+        bookmarks: &[String],
+    ) -> Result<Option<String>, Error> {
+        if let Some(home) = self.home.lock().unwrap().clone() {
+            return Ok(home);
+        }
+        let _refresh = self.refresh.lock().await;
+        if let Some(home) = self.home.lock().unwrap().clone() {
+            return Ok(home);
+        }
+        let routers = self
+            .table(None)
+            .map(|table| table.routers)
+            .unwrap_or_default();
+        let table = self.discover(routers, None, bookmarks).await?;
+        let home = table.database.clone();
+        self.tables.lock().unwrap().insert(home.clone(), table);
+        *self.home.lock().unwrap() = Some(home.clone());
+        Ok(home)
+    }
+
+    fn pool(&self, address: &str) -> Pool {
+        let mut pools = self.pools.lock().unwrap();
+        pools
+            .entry(address.to_string())
+            .or_insert_with(|| Pool::new(address, self.config.clone()))
+            .clone()
+    }
+
+    // A connection to a member serving `mode` for `database`, trying them in
+    // turn, starting from a different one each time. A table fetched on the
+    // way reflects at least the writes of `bookmarks`.
+    pub(crate) async fn acquire(
+        &self,
+        mode: AccessMode,
+        database: Option<&str>,
+        bookmarks: &[String],
+    ) -> Result<PooledConnection, Error> {
+        let mut servers = self.servers(mode, database, bookmarks).await?;
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % servers.len();
+        servers.rotate_left(start);
+        let mut last_error = None;
+        for address in servers {
+            match self.pool(&address).acquire().await {
+                Ok(connection) => return Ok(connection),
+                Err(error @ (Error::Connection(_) | Error::Timeout(_))) => {
+                    self.forget(&address);
+                    last_error = Some(error);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        Err(Error::Routing(format!(
+            "none of the {} could be reached: {}",
+            role(mode),
+            last_error.unwrap()
+        )))
+    }
+
+    fn fresh_servers(&self, mode: AccessMode, database: Option<&str>) -> Option<Vec<String>> {
+        self.table(database)
+            .filter(|table| table.is_fresh(mode))
+            .map(|table| table.servers(mode).to_vec())
+    }
+
+    async fn servers(
+        &self,
+        mode: AccessMode,
+        database: Option<&str>,
+        bookmarks: &[String],
+    ) -> Result<Vec<String>, Error> {
+        let key = self.key(database);
+        let database = key.as_deref();
+        if let Some(servers) = self.fresh_servers(mode, database) {
+            return Ok(servers);
+        }
+        let _refresh = self.refresh.lock().await;
+        // Another session may have refreshed the table while this one waited.
+        if let Some(servers) = self.fresh_servers(mode, database) {
+            return Ok(servers);
+        }
+        let routers = self
+            .table(database)
+            .map(|table| table.routers)
+            .unwrap_or_default();
+        let table = self.discover(routers, database, bookmarks).await?;
+        self.tables.lock().unwrap().insert(key.clone(), table);
+        self.fresh_servers(mode, database).ok_or_else(|| {
+            Error::Routing(format!(
+                "the routing table for {} has no {}",
+                database.unwrap_or("the default database"),
+                role(mode)
+            ))
+        })
+    }
+
+    // Asks the known routers, then the seed, for a new routing table. Client
+    // errors such as an unknown database or bad credentials are the same
+    // everywhere, so they end the search.
+    async fn discover(
+        &self,
+        mut routers: Vec<String>,
+        database: Option<&str>,
+        bookmarks: &[String],
+    ) -> Result<RoutingTable, Error> {
+        if !routers.contains(&self.seed) {
+            routers.push(self.seed.clone());
+        }
+        let mut last_error = None;
+        for router in routers {
+            match self.fetch(&router, database, bookmarks).await {
+                Ok(table) if !table.routers.is_empty() => return Ok(table),
+                Ok(_) => last_error = Some(Error::protocol("routing table has no routers")),
+                Err(error @ Error::Server { .. }) if !error.is_retriable() => return Err(error),
+                Err(error) => last_error = Some(error),
+            }
+            self.forget(&router);
+        }
+        Err(Error::Routing(format!(
+            "no router returned a routing table: {}",
+            last_error.unwrap()
+        )))
+    }
+
+    async fn fetch(
+        &self,
+        router: &str,
+        database: Option<&str>,
+        bookmarks: &[String],
+    ) -> Result<RoutingTable, Error> {
+        let mut connection = self.pool(router).acquire().await?;
+        let context = routing_context(&self.config);
+        let rt = fetch_routing_table(&mut connection, context, database, bookmarks).await?;
+        RoutingTable::parse(rt, database)
+    }
+
+    // Drops a member that failed from every routing table, so the next
+    // request goes elsewhere or triggers a refresh.
+    pub(crate) fn forget(&self, address: &str) {
+        for table in self.tables.lock().unwrap().values_mut() {
+            table.forget(address);
+        }
+    }
+
+    // A writer that says it can't take writes has lost its leadership.
+    pub(crate) fn forget_writer(&self, address: &str) {
+        for table in self.tables.lock().unwrap().values_mut() {
+            table.writers.retain(|writer| writer != address);
+        }
+    }
+
+    pub(crate) async fn close(&self) {
+        let pools: Vec<Pool> = self.pools.lock().unwrap().values().cloned().collect();
+        for pool in pools {
+            pool.close().await;
+        }
+    }
+}
+
+// ROUTE arrived with Bolt 4.3. Older servers expose the table through a
+// procedure, run against the system database from 4.0 on, and can't be given
+// bookmarks.
+async fn fetch_routing_table(
+    connection: &mut Connection,
+    context: Dictionary,
+    database: Option<&str>,
+    bookmarks: &[String],
+) -> Result<Dictionary, Error> {
+    let version = connection.version();
+    let db = match database {
+        Some(database) => MessageValue::String(database.to_string()),
+        None => MessageValue::Null,
+    };
+    if version >= BoltVersion::new(4, 3) {
+        let route: MessageStructure = if version >= BoltVersion::new(4, 4) {
+            let mut extra = Dictionary::new();
+            if let MessageValue::String(_) = db {
+                extra.insert("db".to_string(), db);
+            }
+            Route {
+                routing: context,
+                bookmarks: bookmarks.to_vec(),
+                extra,
+            }
+            .into()
+        } else {
+            MessageStructure::new(
+                ROUTE,
+                vec![
+                    MessageValue::Map(context),
+                    MessageValue::from(bookmarks.to_vec()),
+                    db,
+                ],
+            )
+        };
+        let mut success = connection.request(route).await?;
+        return match success.metadata.remove("rt") {
+            Some(rt) => into_dictionary(rt, "routing table"),
+            None => Err(Error::protocol("ROUTE SUCCESS is missing rt")),
+        };
+    }
+
+    let mut parameters = Dictionary::from([("context".to_string(), MessageValue::Map(context))]);
+    let mut extra = Dictionary::new();
+    let query = if version >= BoltVersion::new(4, 0) {
+        parameters.insert("database".to_string(), db);
+        extra.insert("db".to_string(), MessageValue::String("system".to_string()));
+        "CALL dbms.routing.getRoutingTable($context, $database)"
+    } else {
+        "CALL dbms.cluster.routing.getRoutingTable($context)"
+    };
+    let run = Run {
+        query: query.to_string(),
+        parameters,
+        extra,
+    };
+    let success = connection.request(run).await?;
+    connection.start_stream();
+    let keys = field_names(&success);
+    let values = match connection.next_record(ALL).await? {
+        Some(values) => values,
+        None => return Err(Error::protocol("routing procedure returned no table")),
+    };
+    connection.finish_stream().await?;
+    Ok(keys.iter().cloned().zip(values).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bolt::messages::{self, Failure, Response};
+    use crate::bolt::stub::{fields, record, stub_server, success, tags};
+    use crate::session::SessionConfig;
+    use crate::Driver;
+
+    fn string(s: &str) -> MessageValue {
+        MessageValue::String(s.to_string())
+    }
+
+    fn rt(routers: &[&str], readers: &[&str], writers: &[&str]) -> MessageValue {
+        let server = |role: &str, addresses: &[&str]| {
+            MessageValue::Map(Dictionary::from([
+                ("role".to_string(), string(role)),
+                (
+                    "addresses".to_string(),
+                    MessageValue::List(addresses.iter().map(|a| string(a)).collect()),
+                ),
+            ]))
+        };
+        MessageValue::Map(Dictionary::from([
+            ("ttl".to_string(), MessageValue::Int(300)),
+            ("db".to_string(), string("neo4j")),
+            (
+                "servers".to_string(),
+                MessageValue::List(vec![
+                    server("ROUTE", routers),
+                    server("READ", readers),
+                    server("WRITE", writers),
+                ]),
+            ),
+        ]))
+    }
+
+    #[test]
+    fn parses_routing_tables() {
+        let rt = match rt(&["a:1", "b:1"], &["b:1"], &["a:1"]) {
+            MessageValue::Map(rt) => rt,
+            _ => unreachable!(),
+        };
+        let mut table = RoutingTable::parse(rt, None).unwrap();
+        assert_eq!(table.database.as_deref(), Some("neo4j"));
+        assert_eq!(table.routers, ["a:1", "b:1"]);
+        assert_eq!(table.servers(AccessMode::Read), ["b:1"]);
+        assert!(table.is_fresh(AccessMode::Write));
+        table.forget("a:1");
+        assert!(!table.is_fresh(AccessMode::Write));
+        assert!(table.is_fresh(AccessMode::Read));
+
+        assert!(RoutingTable::parse(Dictionary::new(), None).is_err());
+    }
+
+    #[tokio::test]
+    async fn routes_reads_and_writes_to_their_members() {
+        let (reader, reader_server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&["n"]))])],
+            vec![record(vec![MessageValue::TinyInt(1)]), success(vec![])],
+        ]])
+        .await;
+        let (writer, writer_server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&[]))])],
+            vec![success(vec![])],
+            vec![success(vec![])],
+        ]])
+        .await;
+        // The stub router only routes, so it stays out of the table.
+        let (router, router_server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![(
+                "rt",
+                rt(&[&writer], &[&reader], &[&writer]),
+            )])],
+        ]])
+        .await;
+
+        let driver = Driver::new(&format!("neo4j://{}?region=eu", router), crate::Auth::None);
+        let driver = driver.unwrap();
+        let config = SessionConfig {
+            default_mode: AccessMode::Read,
+            ..SessionConfig::default()
+        };
+        let mut session = driver.session_with(config.clone()).await.unwrap();
+        let result = session.run("RETURN 1 AS n", Dictionary::new()).await;
+        assert_eq!(result.unwrap().collect().await.unwrap().len(), 1);
+        session
+            .execute_write(async |tx| {
+                tx.run("CREATE ()", Dictionary::new())
+                    .await?
+                    .consume()
+                    .await
+            })
+            .await
+            .unwrap();
+        // The home database was resolved once, for the whole driver.
+        let table = driver.routing_table(None).unwrap();
+        assert_eq!(table.database.as_deref(), Some("neo4j"));
+        driver.session_with(config).await.unwrap();
+
+        let requests = router_server.await.unwrap().remove(0);
+        assert_eq!(tags(&requests), [messages::HELLO, messages::ROUTE]);
+        let route = Route::try_from(requests[1].clone()).unwrap();
+        assert!(!route.extra.contains_key("db"));
+        assert_eq!(route.routing["region"], string("eu"));
+        assert_eq!(route.routing["address"], string(&router));
+        let hello = messages::Hello::try_from(requests[0].clone()).unwrap();
+        assert_eq!(hello.extra["routing"], MessageValue::Map(route.routing));
+        let requests = reader_server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [messages::HELLO, messages::RUN, messages::PULL]
+        );
+        // Queries go to the resolved database even if the home one moves.
+        let run = Run::try_from(requests[1].clone()).unwrap();
+        assert_eq!(run.extra["db"], string("neo4j"));
+        let requests = writer_server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [
+                messages::HELLO,
+                messages::BEGIN,
+                messages::RUN,
+                messages::DISCARD,
+                messages::COMMIT
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rediscovers_when_the_leader_moves() {
+        let not_a_leader = Failure {
+            code: "Neo.ClientError.Cluster.NotALeader".to_string(),
+            message: "No write operations are allowed on this database".to_string(),
+            metadata: Dictionary::new(),
+        };
+        let (old, old_server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![Response::Failure(not_a_leader).into()],
+            vec![success(vec![])],
+        ]])
+        .await;
+        let (new, new_server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&[]))])],
+            vec![success(vec![])],
+            vec![success(vec![])],
+        ]])
+        .await;
+        // Nothing listens on the listed router, so rediscovery falls back to
+        // the seed.
+        let gone = "127.0.0.1:1";
+        let (router, router_server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![("rt", rt(&[gone], &[&old], &[&old]))])],
+            vec![success(vec![("rt", rt(&[gone], &[&old], &[&new]))])],
+        ]])
+        .await;
+
+        let mut config = Config::from_uri(&format!("neo4j://{}", router)).unwrap();
+        config.retry.initial_delay = Duration::from_millis(1);
+        let driver = Driver::with_config(config);
+        let mut session = driver.session().await.unwrap();
+        let mut attempts = 0;
+        session
+            .execute_write(async |tx| {
+                attempts += 1;
+                tx.run("CREATE ()", Dictionary::new())
+                    .await?
+                    .consume()
+                    .await
+            })
+            .await
+            .unwrap();
+        assert_eq!(attempts, 1);
+        let table = driver.routing_table(None).unwrap();
+        assert_eq!(table.writers, [new.as_str()]);
+
+        let requests = router_server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [messages::HELLO, messages::ROUTE, messages::ROUTE]
+        );
+        let requests = old_server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [messages::HELLO, messages::BEGIN, messages::RESET]
+        );
+        let requests = new_server.await.unwrap().remove(0);
+        assert_eq!(tags(&requests)[..2], [messages::HELLO, messages::BEGIN]);
+    }
+}
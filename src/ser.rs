@@ -0,0 +1,522 @@
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::bolt::message::MessageValue;
+use crate::bolt::messages::Dictionary;
+use crate::error::Error;
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        Error::serialization(message.to_string())
+    }
+}
+
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<MessageValue, Error> {
+    value.serialize(Serializer)
+}
+
+// Query parameters from a struct or map, one per field or entry.
+pub fn to_params<T: Serialize + ?Sized>(value: &T) -> Result<Dictionary, Error> {
+    match to_value(value)? {
+        MessageValue::Map(params) => Ok(params),
+        other => Err(Error::serialization(format!(
+            "parameters should serialize to a map, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+// Mirrors `de`: unit variants become strings, the others single-entry maps
+// from the variant name to its contents.
+pub struct Serializer;
+
+fn variant(name: &str, value: MessageValue) -> MessageValue {
+    MessageValue::Map(Dictionary::from([(name.to_string(), value)]))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = MessageValue;
+    type Error = Error;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, v: bool) -> Result<MessageValue, Error> {
+        Ok(MessageValue::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<MessageValue, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<MessageValue, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<MessageValue, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<MessageValue, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<MessageValue, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<MessageValue, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<MessageValue, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<MessageValue, Error> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) => Err(Error::serialization(format!(
+                "{} is too large for a Bolt integer",
+                v
+            ))),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<MessageValue, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<MessageValue, Error> {
+        Ok(v.into())
+    }
+
+    fn serialize_char(self, v: char) -> Result<MessageValue, Error> {
+        Ok(MessageValue::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<MessageValue, Error> {
+        Ok(MessageValue::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<MessageValue, Error> {
+        Ok(MessageValue::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<MessageValue, Error> {
+        Ok(MessageValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<MessageValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<MessageValue, Error> {
+        Ok(MessageValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<MessageValue, Error> {
+        Ok(MessageValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<MessageValue, Error> {
+        Ok(MessageValue::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<MessageValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        name: &'static str,
+        value: &T,
+    ) -> Result<MessageValue, Error> {
+        Ok(variant(name, to_value(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, Error> {
+        Ok(SerializeList {
+            variant: None,
+            list: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, Error> {
+        Ok(SerializeList {
+            variant: Some(variant),
+            list: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            variant: None,
+            map: Dictionary::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            variant: Some(variant),
+            map: Dictionary::new(),
+            key: None,
+        })
+    }
+}
+
+pub struct SerializeList {
+    variant: Option<&'static str>,
+    list: Vec<MessageValue>,
+}
+
+impl SerializeList {
+    fn end(self) -> Result<MessageValue, Error> {
+        let list = MessageValue::List(self.list);
+        Ok(match self.variant {
+            Some(name) => variant(name, list),
+            None => list,
+        })
+    }
+}
+
+macro_rules! serialize_list {
+    ($($trait:ident, $method:ident);*) => {
+        $(impl ser::$trait for SerializeList {
+            type Ok = MessageValue;
+            type Error = Error;
+
+            fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+                self.list.push(to_value(value)?);
+                Ok(())
+            }
+
+            fn end(self) -> Result<MessageValue, Error> {
+                SerializeList::end(self)
+            }
+        })*
+    };
+}
+
+serialize_list!(
+    SerializeSeq, serialize_element;
+    SerializeTuple, serialize_element;
+    SerializeTupleStruct, serialize_field;
+    SerializeTupleVariant, serialize_field
+);
+
+pub struct SerializeMap {
+    variant: Option<&'static str>,
+    map: Dictionary,
+    key: Option<String>,
+}
+
+impl SerializeMap {
+    fn end(self) -> Result<MessageValue, Error> {
+        let map = MessageValue::Map(self.map);
+        Ok(match self.variant {
+            Some(name) => variant(name, map),
+            None => map,
+        })
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = MessageValue;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        self.map.insert(key, to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<MessageValue, Error> {
+        SerializeMap::end(self)
+    }
+}
+
+macro_rules! serialize_fields {
+    ($($trait:ident),*) => {
+        $(impl ser::$trait for SerializeMap {
+            type Ok = MessageValue;
+            type Error = Error;
+
+            fn serialize_field<T: Serialize + ?Sized>(
+                &mut self,
+                key: &'static str,
+                value: &T,
+            ) -> Result<(), Error> {
+                self.map.insert(key.to_string(), to_value(value)?);
+                Ok(())
+            }
+
+            fn end(self) -> Result<MessageValue, Error> {
+                SerializeMap::end(self)
+            }
+        })*
+    };
+}
+
+serialize_fields!(SerializeStruct, SerializeStructVariant);
+
+// Bolt map keys are strings; chars and unit variants pass as well.
+struct KeySerializer;
+
+fn key_error() -> Error {
+    Error::serialization("map keys should be strings")
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_str(self, v: &str) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_unit(self) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(key_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::de::from_map;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Role {
+        Admin,
+        Guest { until: i64 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Person {
+        name: String,
+        age: u32,
+        nickname: Option<String>,
+        roles: Vec<Role>,
+    }
+
+    #[test]
+    fn serializes_structs_into_parameters() {
+        let person = Person {
+            name: "Alice".to_string(),
+            age: 42,
+            nickname: None,
+            roles: vec![Role::Admin, Role::Guest { until: 1 << 40 }],
+        };
+        let params = to_params(&person).unwrap();
+        assert_eq!(params["name"], MessageValue::String("Alice".to_string()));
+        assert_eq!(params["age"], MessageValue::TinyInt(42));
+        assert_eq!(params["nickname"], MessageValue::Null);
+        assert_eq!(from_map::<Person>(params).unwrap(), person);
+
+        assert!(to_params(&[1, 2]).is_err());
+        assert!(to_value(&u64::MAX).is_err());
+        let keyed_by_number = BTreeMap::from([(1, "one")]);
+        assert!(to_value(&keyed_by_number).is_err());
+    }
+}
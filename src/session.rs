@@ -0,0 +1,892 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::bolt::connection::Connection;
+use crate::bolt::handshake::BoltVersion;
+use crate::bolt::message::MessageValue;
+use crate::bolt::messages::{Begin, Commit, Dictionary, Rollback, Run, Success};
+use crate::bolt::pool::PooledConnection;
+use crate::bookmarks::{BookmarkManager, Bookmarks};
+use crate::config::Config;
+use crate::driver::Connector;
+use crate::error::Error;
+use crate::retry::RetryConfig;
+use crate::stream::RecordStream;
+
+// A routing session holds a connection to one cluster member at a time, and
+// swaps it for another when a transaction needs a different access mode.
+pub struct Session {
+    connector: Connector,
+    connection: Option<PooledConnection>,
+    // What the current connection was acquired for.
+    mode: AccessMode,
+    database: Option<String>,
+    default_mode: AccessMode,
+    default_database: Option<String>,
+    bookmarks: Bookmarks,
+    fetch_size: i64,
+    retry: RetryConfig,
+}
+
+// Dropping a transaction without committing it rolls it back, before the
+// connection's next request.
+pub struct Transaction<'a> {
+    connection: &'a mut Connection,
+    bookmarks: &'a mut Bookmarks,
+    fetch_size: i64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessMode {
+    Read,
+    #[default]
+    Write,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SessionConfig {
+    // Used for auto-commit queries and `begin_transaction`; routing drivers
+    // send these to a reader or writer to match.
+    pub default_mode: AccessMode,
+    // For transactions that don't name their own. None means the user's home
+    // database, which a routing driver resolves once and then pins the
+    // session to. Needs Bolt 4.0.
+    pub database: Option<String>,
+    // The first transaction waits for these; later ones for the bookmark of
+    // the one before.
+    pub bookmarks: Vec<String>,
+    // Chains this session after the others sharing the manager.
+    pub bookmark_manager: Option<Arc<dyn BookmarkManager>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransactionConfig {
+    // Attached to the transaction on the server, e.g. for `SHOW TRANSACTIONS`.
+    pub metadata: Dictionary,
+    // None leaves the server's default in place.
+    pub timeout: Option<Duration>,
+    // Routes the transaction to a reader or writer in a cluster.
+    pub mode: AccessMode,
+    // None means the session's database. Needs Bolt 4.0.
+    pub database: Option<String>,
+    // The transaction starts once the server has caught up with these.
+    pub bookmarks: Vec<String>,
+}
+
+impl TransactionConfig {
+    // The BEGIN extra; defaults are left out.
+    pub(crate) fn extra(&self, version: BoltVersion) -> Result<Dictionary, Error> {
+        let mut extra = Dictionary::new();
+        if !self.bookmarks.is_empty() {
+            let bookmarks = self
+                .bookmarks
+                .iter()
+                .map(|bookmark| MessageValue::String(bookmark.clone()))
+                .collect();
+            extra.insert("bookmarks".to_string(), MessageValue::List(bookmarks));
+        }
+        if let Some(timeout) = self.timeout {
+            // Rounded up: 0 would mean the server's default instead.
+            let millis = timeout.as_nanos().div_ceil(1_000_000);
+            let millis = i64::try_from(millis).unwrap_or(i64::MAX);
+            extra.insert("tx_timeout".to_string(), MessageValue::BigInt(millis));
+        }
+        if !self.metadata.is_empty() {
+            extra.insert(
+                "tx_metadata".to_string(),
+                MessageValue::Map(self.metadata.clone()),
+            );
+        }
+        if self.mode == AccessMode::Read {
+            extra.insert("mode".to_string(), MessageValue::String("r".to_string()));
+        }
+        if let Some(database) = &self.database {
+            if version < BoltVersion::new(4, 0) {
+                return Err(Error::config(format!(
+                    "selecting database {:?} needs Bolt 4.0, the server speaks {}",
+                    database, version
+                )));
+            }
+            extra.insert("db".to_string(), MessageValue::String(database.clone()));
+        }
+        Ok(extra)
+    }
+}
+
+pub(crate) fn field_names(success: &Success) -> Arc<Vec<String>> {
+    let names = match success.metadata.get("fields") {
+        Some(MessageValue::List(fields)) => fields
+            .iter()
+            .filter_map(|field| match field {
+                MessageValue::String(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    Arc::new(names)
+}
+
+// Auto-commit queries pass their session's bookmarks, to be replaced by the
+// one the query leaves.
+async fn run<'a>(
+    connection: &'a mut Connection,
+    query: &str,
+    params: Dictionary,
+    extra: Dictionary,
+    bookmarks: Option<&'a mut Bookmarks>,
+    fetch_size: i64,
+) -> Result<RecordStream<'a>, Error> {
+    let run = Run {
+        query: query.to_string(),
+        parameters: params,
+        extra,
+    };
+    let success = connection.request(run).await?;
+    connection.start_stream();
+    let keys = field_names(&success);
+    Ok(RecordStream::new(
+        connection,
+        keys,
+        success.metadata,
+        bookmarks,
+        fetch_size,
+    ))
+}
+
+impl Session {
+    pub(crate) async fn new(
+        connector: Connector,
+        config: SessionConfig,
+        driver: &Config,
+    ) -> Result<Session, Error> {
+        let mut bookmarks = Bookmarks::new(config.bookmarks, config.bookmark_manager);
+        let current = bookmarks.current();
+        let database = match config.database {
+            Some(database) => Some(database),
+            None => connector.home_database(&current).await?,
+        };
+        let connection = connector
+            .acquire(config.default_mode, database.as_deref(), &current)
+            .await?;
+        Ok(Session {
+            connector,
+            connection: Some(connection),
+            mode: config.default_mode,
+            database: database.clone(),
+            default_mode: config.default_mode,
+            default_database: database,
+            bookmarks,
+            fetch_size: driver.fetch_size,
+            retry: driver.retry.clone(),
+        })
+    }
+
+    // Makes the current connection one usable for `mode`, keeping it if it
+    // is. A stream dropped before its end is discarded first, so that its
+    // bookmark is in before the next request.
+    async fn connect(&mut self, mode: AccessMode, database: Option<String>) -> Result<(), Error> {
+        if let Some(connection) = &mut self.connection {
+            if !connection.is_broken() {
+                connection.finish_stream().await?;
+                self.bookmarks.update(connection.take_bookmark());
+            }
+        }
+        let reusable = match &self.connection {
+            Some(connection) => {
+                let same = mode == self.mode && database == self.database;
+                !connection.is_broken() && (same || !self.connector.is_routing())
+            }
+            None => false,
+        };
+        if !reusable {
+            // Give the old connection back first: it may hold the last permit.
+            self.connection = None;
+            let bookmarks = self.bookmarks.current();
+            let connection = self
+                .connector
+                .acquire(mode, database.as_deref(), &bookmarks)
+                .await?;
+            self.connection = Some(connection);
+            self.mode = mode;
+            self.database = database;
+        }
+        Ok(())
+    }
+
+    // Connects for a transaction or auto-commit query, which also waits for
+    // the session's bookmarks.
+    async fn prepare(&mut self, config: &mut TransactionConfig) -> Result<(), Error> {
+        if config.database.is_none() {
+            config.database = self.default_database.clone();
+        }
+        self.connect(config.mode, config.database.clone()).await?;
+        for bookmark in self.bookmarks.current() {
+            if !config.bookmarks.contains(&bookmark) {
+                config.bookmarks.push(bookmark);
+            }
+        }
+        Ok(())
+    }
+
+    // The bookmarks the session started with, or the one left by its last
+    // transaction.
+    pub fn last_bookmarks(&self) -> &[String] {
+        self.bookmarks.last()
+    }
+
+    // Runs a query in its own auto-commit transaction.
+    pub async fn run(
+        &mut self,
+        query: &str,
+        params: Dictionary,
+    ) -> Result<RecordStream<'_>, Error> {
+        let config = TransactionConfig {
+            mode: self.default_mode,
+            ..TransactionConfig::default()
+        };
+        self.run_with(query, params, config).await
+    }
+
+    // An auto-commit query with its own database, access mode, timeout or
+    // metadata.
+    pub async fn run_with(
+        &mut self,
+        query: &str,
+        params: Dictionary,
+        mut config: TransactionConfig,
+    ) -> Result<RecordStream<'_>, Error> {
+        self.prepare(&mut config).await?;
+        let connection = self.connection.as_mut().unwrap();
+        let extra = config.extra(connection.version())?;
+        let bookmarks = Some(&mut self.bookmarks);
+        run(connection, query, params, extra, bookmarks, self.fetch_size).await
+    }
+
+    pub async fn begin_transaction(&mut self) -> Result<Transaction<'_>, Error> {
+        self.begin_transaction_with(TransactionConfig {
+            mode: self.default_mode,
+            ..TransactionConfig::default()
+        })
+        .await
+    }
+
+    pub async fn begin_transaction_with(
+        &mut self,
+        mut config: TransactionConfig,
+    ) -> Result<Transaction<'_>, Error> {
+        self.prepare(&mut config).await?;
+        let connection = self.connection.as_mut().unwrap();
+        let extra = config.extra(connection.version())?;
+        connection.request(Begin { extra }).await?;
+        Ok(Transaction {
+            connection,
+            bookmarks: &mut self.bookmarks,
+            fetch_size: self.fetch_size,
+        })
+    }
+
+    // Runs `work` in a read transaction and commits it, retrying the whole
+    // transaction on retriable errors. `work` may run more than once, so it
+    // should have no other side effects.
+    pub async fn execute_read<T, F>(&mut self, work: F) -> Result<T, Error>
+    where
+        F: AsyncFnMut(&mut Transaction<'_>) -> Result<T, Error>,
+    {
+        self.execute(AccessMode::Read, work).await
+    }
+
+    pub async fn execute_write<T, F>(&mut self, work: F) -> Result<T, Error>
+    where
+        F: AsyncFnMut(&mut Transaction<'_>) -> Result<T, Error>,
+    {
+        self.execute(AccessMode::Write, work).await
+    }
+
+    async fn execute<T, F>(&mut self, mode: AccessMode, mut work: F) -> Result<T, Error>
+    where
+        F: AsyncFnMut(&mut Transaction<'_>) -> Result<T, Error>,
+    {
+        let config = TransactionConfig {
+            mode,
+            ..TransactionConfig::default()
+        };
+        let started = Instant::now();
+        let mut retry = 0;
+        loop {
+            let error = match self.attempt(&config, &mut work).await {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            if !error.is_retriable() || started.elapsed() >= self.retry.max_retry_time {
+                return Err(error);
+            }
+            // Members that failed are routed around, so the next attempt
+            // starts on a fresh connection.
+            if self.connector.is_routing() {
+                if let Some(connection) = self.connection.take() {
+                    self.connector.on_error(connection.address(), &error);
+                }
+            }
+            tokio::time::sleep(self.retry.delay(retry)).await;
+            retry += 1;
+        }
+    }
+
+    // A failed attempt leaves its transaction to be rolled back on drop.
+    async fn attempt<T, F>(&mut self, config: &TransactionConfig, work: &mut F) -> Result<T, Error>
+    where
+        F: AsyncFnMut(&mut Transaction<'_>) -> Result<T, Error>,
+    {
+        let mut tx = self.begin_transaction_with(config.clone()).await?;
+        let value = work(&mut tx).await?;
+        tx.commit().await?;
+        Ok(value)
+    }
+
+    // Gives the connection back to the pool once anything left unread is
+    // skipped. Dropping the session also gives it back, to be cleaned up on
+    // its next use.
+    pub async fn close(mut self) -> Result<(), Error> {
+        if let Some(connection) = &mut self.connection {
+            connection.finish_stream().await?;
+            self.bookmarks.update(connection.take_bookmark());
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Transaction<'a> {
+    pub async fn run(
+        &mut self,
+        query: &str,
+        params: Dictionary,
+    ) -> Result<RecordStream<'_>, Error> {
+        let extra = Dictionary::new();
+        run(self.connection, query, params, extra, None, self.fetch_size).await
+    }
+
+    // A failed query has already rolled the transaction back, so there is
+    // nothing left to commit.
+    pub async fn commit(self) -> Result<(), Error> {
+        if !self.connection.in_transaction() {
+            return Err(Error::protocol(
+                "the transaction was rolled back after a failure and can't be committed",
+            ));
+        }
+        self.connection.request(Commit).await?;
+        self.bookmarks.update(self.connection.take_bookmark());
+        Ok(())
+    }
+
+    pub async fn rollback(self) -> Result<(), Error> {
+        if !self.connection.in_transaction() {
+            return Ok(());
+        }
+        self.connection.request(Rollback).await?;
+        Ok(())
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        self.connection.abandon_transaction();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Auth;
+    use crate::bolt::chaos::ChaosConfig;
+    use crate::bolt::messages::{self, Failure, Response};
+    use crate::bolt::stub::{fields, record, stub_server, success, tags};
+    use crate::bookmarks::InMemoryBookmarkManager;
+    use crate::driver::Driver;
+
+    #[tokio::test]
+    async fn runs_auto_commit_queries() {
+        let hello = vec![success(vec![(
+            "server",
+            MessageValue::String("Neo4j/4.4.0".to_string()),
+        )])];
+        let (address, server) = stub_server(vec![vec![
+            hello,
+            vec![success(vec![("fields", fields(&["n"]))])],
+            vec![
+                record(vec![MessageValue::TinyInt(1)]),
+                record(vec![MessageValue::TinyInt(2)]),
+                success(vec![]),
+            ],
+            vec![],
+        ]])
+        .await;
+
+        let driver = Driver::new(&address, Auth::basic("neo4j", "secret")).unwrap();
+        let mut session = driver.session().await.unwrap();
+        let params = Dictionary::from([("x".to_string(), MessageValue::TinyInt(1))]);
+        let result = session
+            .run("UNWIND [1, 2] AS n RETURN n", params)
+            .await
+            .unwrap();
+        assert_eq!(result.keys(), ["n"]);
+        let records = result.collect().await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].get("n"), Some(&MessageValue::TinyInt(2)));
+        session.close().await.unwrap();
+        // The connection went back to the pool; closing the driver says goodbye.
+        driver.close().await;
+
+        let requests = server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [
+                messages::HELLO,
+                messages::RUN,
+                messages::PULL,
+                messages::GOODBYE
+            ]
+        );
+        let hello = messages::Hello::try_from(requests[0].clone()).unwrap();
+        assert_eq!(
+            hello.extra.get("principal"),
+            Some(&MessageValue::String("neo4j".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn commits_and_rolls_back_transactions() {
+        let (address, server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&["n"]))])],
+            vec![record(vec![MessageValue::TinyInt(1)]), success(vec![])],
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![success(vec![])],
+        ]])
+        .await;
+
+        let driver = Driver::new(&address, Auth::None).unwrap();
+        let mut session = driver.session().await.unwrap();
+        let mut tx = session.begin_transaction().await.unwrap();
+        let result = tx.run("RETURN 1 AS n", Dictionary::new()).await.unwrap();
+        assert_eq!(result.collect().await.unwrap().len(), 1);
+        tx.commit().await.unwrap();
+        let tx = session.begin_transaction().await.unwrap();
+        tx.rollback().await.unwrap();
+
+        let requests = server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [
+                messages::HELLO,
+                messages::BEGIN,
+                messages::RUN,
+                messages::PULL,
+                messages::COMMIT,
+                messages::BEGIN,
+                messages::ROLLBACK,
+            ]
+        );
+    }
+
+    #[test]
+    fn transaction_config_fills_the_begin_extra() {
+        let config = TransactionConfig {
+            metadata: Dictionary::from([(
+                "app".to_string(),
+                MessageValue::String("shop".to_string()),
+            )]),
+            timeout: Some(Duration::from_secs(5)),
+            mode: AccessMode::Read,
+            database: Some("movies".to_string()),
+            bookmarks: vec!["bm:1".to_string()],
+        };
+        let extra = config.extra(BoltVersion::new(4, 4)).unwrap();
+        assert_eq!(extra["tx_timeout"], MessageValue::BigInt(5000));
+        assert_eq!(extra["mode"], MessageValue::String("r".to_string()));
+        assert_eq!(extra["db"], MessageValue::String("movies".to_string()));
+        assert_eq!(
+            extra["bookmarks"],
+            MessageValue::List(vec![MessageValue::String("bm:1".to_string())])
+        );
+        assert_eq!(
+            extra["tx_metadata"],
+            MessageValue::Map(config.metadata.clone())
+        );
+        assert!(config.extra(BoltVersion::new(3, 0)).is_err());
+
+        let brief = TransactionConfig {
+            timeout: Some(Duration::from_micros(10)),
+            ..TransactionConfig::default()
+        };
+        let extra = brief.extra(BoltVersion::new(4, 4)).unwrap();
+        assert_eq!(extra["tx_timeout"], MessageValue::BigInt(1));
+
+        let defaults = TransactionConfig::default();
+        assert!(defaults.extra(BoltVersion::new(3, 0)).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn dropped_transactions_are_rolled_back() {
+        let (address, server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&["n"]))])],
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&["n"]))])],
+            vec![success(vec![])],
+        ]])
+        .await;
+
+        let driver = Driver::new(&address, Auth::None).unwrap();
+        let mut session = driver.session().await.unwrap();
+        let mut tx = session
+            .begin_transaction_with(TransactionConfig {
+                mode: AccessMode::Read,
+                ..TransactionConfig::default()
+            })
+            .await
+            .unwrap();
+        tx.run("RETURN 1 AS n", Dictionary::new()).await.unwrap();
+        drop(tx);
+        let result = session.run("RETURN 2 AS n", Dictionary::new()).await;
+        assert!(result.unwrap().collect().await.unwrap().is_empty());
+
+        let requests = server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [
+                messages::HELLO,
+                messages::BEGIN,
+                messages::RUN,
+                messages::DISCARD,
+                messages::ROLLBACK,
+                messages::RUN,
+                messages::PULL,
+            ]
+        );
+        let begin = messages::Begin::try_from(requests[1].clone()).unwrap();
+        assert_eq!(begin.extra["mode"], MessageValue::String("r".to_string()));
+    }
+
+    #[tokio::test]
+    async fn managed_transactions_retry_transient_failures() {
+        let failure = Failure {
+            code: "Neo.TransientError.General.DatabaseUnavailable".to_string(),
+            message: "Database unavailable".to_string(),
+            metadata: Dictionary::new(),
+        };
+        let (address, server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![Response::Failure(failure).into()],
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&["n"]))])],
+            vec![record(vec![MessageValue::TinyInt(1)]), success(vec![])],
+            vec![success(vec![])],
+        ]])
+        .await;
+
+        let mut config = Config::from_uri(&address).unwrap();
+        config.retry.initial_delay = Duration::from_millis(1);
+        let driver = Driver::with_config(config);
+        let mut session = driver.session().await.unwrap();
+        let mut attempts = 0;
+        let records = session
+            .execute_write(async |tx| {
+                attempts += 1;
+                let result = tx.run("CREATE (n) RETURN 1 AS n", Dictionary::new());
+                result.await?.collect().await
+            })
+            .await
+            .unwrap();
+        assert_eq!((records.len(), attempts), (1, 2));
+
+        let requests = server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [
+                messages::HELLO,
+                messages::BEGIN,
+                messages::RUN,
+                messages::RESET,
+                messages::BEGIN,
+                messages::RUN,
+                messages::PULL,
+                messages::COMMIT,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failures_reset_the_connection() {
+        let failure = Failure {
+            code: "Neo.ClientError.Statement.SyntaxError".to_string(),
+            message: "Invalid input".to_string(),
+            metadata: Dictionary::new(),
+        };
+        let (address, server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![Response::Failure(failure).into()],
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&[]))])],
+            vec![success(vec![])],
+        ]])
+        .await;
+
+        let driver = Driver::new(&address, Auth::None).unwrap();
+        let mut session = driver.session().await.unwrap();
+        let error = session
+            .run("RETRUN 1", Dictionary::new())
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.code(), Some("Neo.ClientError.Statement.SyntaxError"));
+        assert!(!error.is_retriable());
+        let result = session.run("RETURN 1", Dictionary::new()).await.unwrap();
+        assert!(result.collect().await.unwrap().is_empty());
+
+        let requests = server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [
+                messages::HELLO,
+                messages::RUN,
+                messages::RESET,
+                messages::RUN,
+                messages::PULL,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn bookmarks_chain_transactions_and_sessions() {
+        let bookmark = |b: &str| success(vec![("bookmark", MessageValue::String(b.to_string()))]);
+        let (address, server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![bookmark("bm:1")],
+            vec![success(vec![("fields", fields(&[]))])],
+            vec![bookmark("bm:2")],
+            vec![success(vec![])],
+            vec![success(vec![])],
+        ]])
+        .await;
+
+        let driver = Driver::new(&address, Auth::None).unwrap();
+        let manager = Arc::new(InMemoryBookmarkManager::default());
+        let config = SessionConfig {
+            bookmark_manager: Some(manager.clone()),
+            ..SessionConfig::default()
+        };
+        let mut session = driver.session_with(config.clone()).await.unwrap();
+        session
+            .begin_transaction()
+            .await
+            .unwrap()
+            .commit()
+            .await
+            .unwrap();
+        assert_eq!(session.last_bookmarks(), ["bm:1"]);
+        let result = session.run("CREATE ()", Dictionary::new()).await.unwrap();
+        result.consume().await.unwrap();
+        assert_eq!(session.last_bookmarks(), ["bm:2"]);
+        assert_eq!(manager.bookmarks(), ["bm:2"]);
+        session.close().await.unwrap();
+        // A new session on the same manager starts after the first one.
+        let mut session = driver.session_with(config).await.unwrap();
+        session
+            .begin_transaction()
+            .await
+            .unwrap()
+            .rollback()
+            .await
+            .unwrap();
+
+        let requests = server.await.unwrap().remove(0);
+        let bookmarks = |b: &str| MessageValue::List(vec![MessageValue::String(b.to_string())]);
+        let run = messages::Run::try_from(requests[3].clone()).unwrap();
+        assert_eq!(run.extra["bookmarks"], bookmarks("bm:1"));
+        let begin = messages::Begin::try_from(requests[5].clone()).unwrap();
+        assert_eq!(begin.extra["bookmarks"], bookmarks("bm:2"));
+        let begin = messages::Begin::try_from(requests[1].clone()).unwrap();
+        assert!(!begin.extra.contains_key("bookmarks"));
+    }
+
+    #[tokio::test]
+    async fn sessions_and_queries_select_databases() {
+        let hello = vec![success(vec![(
+            "server",
+            MessageValue::String("Neo4j/4.4.0".to_string()),
+        )])];
+        let (address, server) = stub_server(vec![vec![
+            hello,
+            vec![success(vec![("fields", fields(&[]))])],
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&[]))])],
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![success(vec![])],
+        ]])
+        .await;
+
+        let driver = Driver::new(&address, Auth::None).unwrap();
+        let config = SessionConfig {
+            database: Some("movies".to_string()),
+            ..SessionConfig::default()
+        };
+        let mut session = driver.session_with(config).await.unwrap();
+        let result = session.run("RETURN 1", Dictionary::new()).await.unwrap();
+        result.consume().await.unwrap();
+        let config = TransactionConfig {
+            database: Some("system".to_string()),
+            ..TransactionConfig::default()
+        };
+        let result = session.run_with("SHOW DATABASES", Dictionary::new(), config);
+        result.await.unwrap().consume().await.unwrap();
+        session
+            .begin_transaction()
+            .await
+            .unwrap()
+            .commit()
+            .await
+            .unwrap();
+
+        let requests = server.await.unwrap().remove(0);
+        let database = |name: &str| MessageValue::String(name.to_string());
+        let run = messages::Run::try_from(requests[1].clone()).unwrap();
+        assert_eq!(run.extra["db"], database("movies"));
+        let run = messages::Run::try_from(requests[3].clone()).unwrap();
+        assert_eq!(run.extra["db"], database("system"));
+        let begin = messages::Begin::try_from(requests[5].clone()).unwrap();
+        assert_eq!(begin.extra["db"], database("movies"));
+    }
+
+    #[tokio::test]
+    async fn records_nested_past_the_limit_are_refused() {
+        let mut nested = MessageValue::Null;
+        for _ in 0..4 {
+            nested = MessageValue::List(vec![nested]);
+        }
+        let (address, _server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&["n"]))])],
+            vec![record(vec![nested]), success(vec![])],
+        ]])
+        .await;
+
+        let config = crate::config::ConfigBuilder::from_uri(&address)
+            .unwrap()
+            .max_nesting_depth(3)
+            .build();
+        let driver = Driver::with_config(config);
+        let mut session = driver.session().await.unwrap();
+        let result = session.run("RETURN [[[[null]]]]", Dictionary::new()).await;
+        let error = result.unwrap().collect().await.err().unwrap();
+        assert!(matches!(error, Error::NestingTooDeep { max_depth: 3 }));
+    }
+
+    #[tokio::test]
+    async fn managed_transactions_recover_from_dropped_connections() {
+        // The first connection dies partway through the reply to BEGIN.
+        let (address, server) = stub_server(vec![
+            vec![vec![success(vec![])], vec![success(vec![])]],
+            vec![
+                vec![success(vec![])],
+                vec![success(vec![])],
+                vec![success(vec![("fields", fields(&[]))])],
+                vec![success(vec![])],
+                vec![success(vec![])],
+            ],
+        ])
+        .await;
+
+        let mut config = Config::from_uri(&address).unwrap();
+        config.retry.initial_delay = Duration::from_millis(1);
+        config.chaos = Some(ChaosConfig {
+            max_io_size: Some(3),
+            // The handshake reply, HELLO's SUCCESS and part of BEGIN's.
+            truncate_after: Some(4 + 7 + 3),
+            connections: Some(1),
+            ..ChaosConfig::default()
+        });
+        let driver = Driver::with_config(config);
+        let mut session = driver.session().await.unwrap();
+        let mut attempts = 0;
+        session
+            .execute_write(async |tx| {
+                attempts += 1;
+                tx.run("CREATE ()", Dictionary::new())
+                    .await?
+                    .consume()
+                    .await
+            })
+            .await
+            .unwrap();
+        // BEGIN failed before the work ran.
+        assert_eq!(attempts, 1);
+        let pool = driver.pool().unwrap();
+        assert_eq!((pool.in_use_count(), pool.idle_count()), (1, 0));
+        drop(session);
+        assert_eq!((pool.in_use_count(), pool.idle_count()), (0, 1));
+
+        let requests = server.await.unwrap();
+        assert_eq!(tags(&requests[0]), [messages::HELLO, messages::BEGIN]);
+        assert_eq!(
+            tags(&requests[1]),
+            [
+                messages::HELLO,
+                messages::BEGIN,
+                messages::RUN,
+                messages::DISCARD,
+                messages::COMMIT
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn transactions_that_failed_are_already_rolled_back() {
+        let failure = || Failure {
+            code: "Neo.ClientError.Statement.SyntaxError".to_string(),
+            message: "Invalid input".to_string(),
+            metadata: Dictionary::new(),
+        };
+        let (address, server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![Response::Failure(failure()).into()],
+            vec![success(vec![])],
+            vec![success(vec![])],
+            vec![Response::Failure(failure()).into()],
+            vec![success(vec![])],
+        ]])
+        .await;
+
+        let driver = Driver::new(&address, Auth::None).unwrap();
+        let mut session = driver.session().await.unwrap();
+        let mut tx = session.begin_transaction().await.unwrap();
+        assert!(tx.run("RETRUN 1", Dictionary::new()).await.is_err());
+        tx.rollback().await.unwrap();
+        let mut tx = session.begin_transaction().await.unwrap();
+        assert!(tx.run("RETRUN 1", Dictionary::new()).await.is_err());
+        assert!(matches!(tx.commit().await, Err(Error::Protocol(_))));
+
+        let requests = server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [
+                messages::HELLO,
+                messages::BEGIN,
+                messages::RUN,
+                messages::RESET,
+                messages::BEGIN,
+                messages::RUN,
+                messages::RESET,
+            ]
+        );
+    }
+}
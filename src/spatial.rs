@@ -0,0 +1,259 @@
+use crate::bolt::message::{MessageStructure, MessageValue};
+use crate::error::Error;
+
+pub const POINT_2D: u8 = 0x58;
+pub const POINT_3D: u8 = 0x59;
+
+pub const WGS84_2D: i64 = 4326;
+pub const WGS84_3D: i64 = 4979;
+pub const CARTESIAN_2D: i64 = 7203;
+pub const CARTESIAN_3D: i64 = 9157;
+
+// For WGS-84 points x is the longitude and y the latitude.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point2D {
+    pub srid: i64,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point3D {
+    pub srid: i64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Point2D {
+    pub fn new(srid: i64, x: f64, y: f64) -> Point2D {
+        Point2D { srid, x, y }
+    }
+
+    pub fn cartesian(x: f64, y: f64) -> Point2D {
+        Point2D::new(CARTESIAN_2D, x, y)
+    }
+
+    pub fn wgs84(longitude: f64, latitude: f64) -> Point2D {
+        Point2D::new(WGS84_2D, longitude, latitude)
+    }
+}
+
+impl Point3D {
+    pub fn new(srid: i64, x: f64, y: f64, z: f64) -> Point3D {
+        Point3D { srid, x, y, z }
+    }
+
+    pub fn cartesian(x: f64, y: f64, z: f64) -> Point3D {
+        Point3D::new(CARTESIAN_3D, x, y, z)
+    }
+
+    pub fn wgs84(longitude: f64, latitude: f64, height: f64) -> Point3D {
+        Point3D::new(WGS84_3D, longitude, latitude, height)
+    }
+}
+
+// Checks the tag and field count, then reads the SRID and coordinates.
+fn point_fields(
+    structure: MessageStructure,
+    name: &str,
+    tag: u8,
+    coordinates: usize,
+) -> Result<(i64, Vec<f64>), Error> {
+    if structure.tag() != tag || structure.len() != coordinates + 1 {
+        return Err(Error::protocol(format!(
+            "expected {} (0x{:02X}) with {} fields, got tag 0x{:02X} with {}",
+            name,
+            tag,
+            coordinates + 1,
+            structure.tag(),
+            structure.len()
+        )));
+    }
+    let srid = structure[0].as_i64()?;
+    let coordinates = structure.fields()[1..]
+        .iter()
+        .map(MessageValue::as_f64)
+        .collect::<Result<_, _>>()?;
+    Ok((srid, coordinates))
+}
+
+impl From<Point2D> for MessageStructure {
+    fn from(point: Point2D) -> Self {
+        MessageStructure::new(
+            POINT_2D,
+            vec![
+                MessageValue::BigInt(point.srid),
+                MessageValue::Float(point.x),
+                MessageValue::Float(point.y),
+            ],
+        )
+    }
+}
+
+impl From<Point3D> for MessageStructure {
+    fn from(point: Point3D) -> Self {
+        MessageStructure::new(
+            POINT_3D,
+            vec![
+                MessageValue::BigInt(point.srid),
+                MessageValue::Float(point.x),
+                MessageValue::Float(point.y),
+                MessageValue::Float(point.z),
+            ],
+        )
+    }
+}
+
+impl TryFrom<MessageStructure> for Point2D {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let (srid, xy) = point_fields(structure, "Point2D", POINT_2D, 2)?;
+        Ok(Point2D::new(srid, xy[0], xy[1]))
+    }
+}
+
+impl TryFrom<MessageStructure> for Point3D {
+    type Error = Error;
+
+    fn try_from(structure: MessageStructure) -> Result<Self, Self::Error> {
+        let (srid, xyz) = point_fields(structure, "Point3D", POINT_3D, 3)?;
+        Ok(Point3D::new(srid, xyz[0], xyz[1], xyz[2]))
+    }
+}
+
+macro_rules! value_conversions {
+    ($($kind:ident),*) => {
+        $(impl From<$kind> for MessageValue {
+            fn from(point: $kind) -> Self {
+                MessageValue::Structure(point.into())
+            }
+        }
+
+        impl TryFrom<MessageValue> for $kind {
+            type Error = Error;
+
+            fn try_from(value: MessageValue) -> Result<Self, Self::Error> {
+                match value {
+                    MessageValue::Structure(structure) => $kind::try_from(structure),
+                    other => Err(Error::protocol(format!(
+                        "expected {}, got {}",
+                        stringify!($kind),
+                        other.type_name()
+                    ))),
+                }
+            }
+        })*
+    };
+}
+
+value_conversions!(Point2D, Point3D);
+
+// Tuples are taken to be cartesian coordinates.
+impl From<(f64, f64)> for Point2D {
+    fn from((x, y): (f64, f64)) -> Self {
+        Point2D::cartesian(x, y)
+    }
+}
+
+impl From<(f64, f64, f64)> for Point3D {
+    fn from((x, y, z): (f64, f64, f64)) -> Self {
+        Point3D::cartesian(x, y, z)
+    }
+}
+
+impl From<Point2D> for (f64, f64) {
+    fn from(point: Point2D) -> Self {
+        (point.x, point.y)
+    }
+}
+
+impl From<Point3D> for (f64, f64, f64) {
+    fn from(point: Point3D) -> Self {
+        (point.x, point.y, point.z)
+    }
+}
+
+// geo-types has no notion of a coordinate system. Its points are read as
+// WGS-84 longitude/latitude, the usual convention for geographic data.
+#[cfg(feature = "geo-types")]
+mod geo {
+    use super::Point2D;
+
+    impl From<Point2D> for geo_types::Coord<f64> {
+        fn from(point: Point2D) -> Self {
+            geo_types::coord! { x: point.x, y: point.y }
+        }
+    }
+
+    impl From<Point2D> for geo_types::Point<f64> {
+        fn from(point: Point2D) -> Self {
+            geo_types::Point::new(point.x, point.y)
+        }
+    }
+
+    impl From<geo_types::Coord<f64>> for Point2D {
+        fn from(coord: geo_types::Coord<f64>) -> Self {
+            Point2D::wgs84(coord.x, coord.y)
+        }
+    }
+
+    impl From<geo_types::Point<f64>> for Point2D {
+        fn from(point: geo_types::Point<f64>) -> Self {
+            Point2D::wgs84(point.x(), point.y())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bolt::message::{pack, unpack};
+
+    #[test]
+    fn points_round_trip_through_packstream() {
+        let point = Point2D::wgs84(12.5, 55.7);
+        let bytes = pack(point.into()).unwrap();
+        assert_eq!(&bytes[..2], [0xB3, POINT_2D]);
+        assert_eq!(Point2D::try_from(unpack(&bytes).unwrap()).unwrap(), point);
+
+        let point = Point3D::cartesian(1.0, 2.0, 3.0);
+        let bytes = pack(point.into()).unwrap();
+        assert_eq!(Point3D::try_from(unpack(&bytes).unwrap()).unwrap(), point);
+        assert!(Point2D::try_from(unpack(&bytes).unwrap()).is_err());
+    }
+
+    #[test]
+    fn decodes_integer_srids_and_coordinates_of_any_width() {
+        let structure = MessageStructure::new(
+            POINT_2D,
+            vec![
+                MessageValue::SmallInt(7203),
+                MessageValue::TinyInt(1),
+                MessageValue::Float(2.5),
+            ],
+        );
+        assert_eq!(
+            Point2D::try_from(structure).unwrap(),
+            Point2D::cartesian(1.0, 2.5)
+        );
+    }
+
+    #[test]
+    fn converts_to_and_from_tuples() {
+        assert_eq!(Point2D::from((1.0, 2.0)).srid, CARTESIAN_2D);
+        assert_eq!(
+            <(f64, f64, f64)>::from(Point3D::wgs84(1.0, 2.0, 3.0)),
+            (1.0, 2.0, 3.0)
+        );
+    }
+
+    #[cfg(feature = "geo-types")]
+    #[test]
+    fn converts_to_and_from_geo_types() {
+        let point: geo_types::Point<f64> = Point2D::wgs84(12.5, 55.7).into();
+        assert_eq!((point.x(), point.y()), (12.5, 55.7));
+        assert_eq!(Point2D::from(point), Point2D::wgs84(12.5, 55.7));
+    }
+}
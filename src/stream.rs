@@ -0,0 +1,262 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::bolt::connection::Connection;
+use crate::bolt::message::MessageValue;
+use crate::bolt::messages::Dictionary;
+use crate::bookmarks::Bookmarks;
+use crate::error::Error;
+use crate::record::Record;
+use crate::summary::ResultSummary;
+
+type Fetched<'a> = (&'a mut Connection, Result<Option<Vec<MessageValue>>, Error>);
+type Fetch<'a> = Pin<Box<dyn Future<Output = Fetched<'a>> + Send + 'a>>;
+
+// Records of one query, pulled from the server in batches of `fetch_size` as
+// they are read. Dropping the stream early is fine: the rest is discarded
+// before the connection's next request.
+pub struct RecordStream<'a> {
+    keys: Arc<Vec<String>>,
+    // The RUN SUCCESS metadata, half of the summary.
+    run: Dictionary,
+    // An auto-commit query's session bookmarks, updated once it completes.
+    bookmarks: Option<&'a mut Bookmarks>,
+    fetch_size: i64,
+    // The connection is lent to `fetch` while a record is being read, and
+    // handed back with the result.
+    connection: Option<&'a mut Connection>,
+    fetch: Option<Fetch<'a>>,
+}
+
+impl<'a> RecordStream<'a> {
+    pub(crate) fn new(
+        connection: &'a mut Connection,
+        keys: Arc<Vec<String>>,
+        run: Dictionary,
+        bookmarks: Option<&'a mut Bookmarks>,
+        fetch_size: i64,
+    ) -> RecordStream<'a> {
+        RecordStream {
+            keys,
+            run,
+            bookmarks,
+            fetch_size,
+            connection: Some(connection),
+            fetch: None,
+        }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    pub async fn next(&mut self) -> Option<Result<Record, Error>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    pub async fn collect(mut self) -> Result<Vec<Record>, Error> {
+        let mut records = Vec::new();
+        while let Some(record) = self.next().await {
+            records.push(record?);
+        }
+        Ok(records)
+    }
+
+    // Discards the remaining records, if any, and returns the summary.
+    pub async fn consume(mut self) -> Result<ResultSummary, Error> {
+        if let Some(fetch) = self.fetch.take() {
+            let (connection, result) = fetch.await;
+            self.connection = Some(connection);
+            result?;
+        }
+        let end = match self.connection.take() {
+            Some(connection) => {
+                connection.finish_stream().await?;
+                if let Some(bookmarks) = &mut self.bookmarks {
+                    bookmarks.update(connection.take_bookmark());
+                }
+                connection.take_stream_summary()
+            }
+            None => None,
+        };
+        Ok(ResultSummary::new(&self.run, &end.unwrap_or_default()))
+    }
+}
+
+impl<'a> Stream for RecordStream<'a> {
+    type Item = Result<Record, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.fetch.is_none() {
+            let connection = match this.connection.take() {
+                Some(connection) => connection,
+                None => return Poll::Ready(None),
+            };
+            let fetch_size = this.fetch_size;
+            this.fetch = Some(Box::pin(async move {
+                let result = connection.next_record(fetch_size).await;
+                (connection, result)
+            }));
+        }
+        let (connection, result) = match this.fetch.as_mut().unwrap().as_mut().poll(cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => return Poll::Pending,
+        };
+        this.fetch = None;
+        if let (Ok(None), Some(bookmarks)) = (&result, &mut this.bookmarks) {
+            bookmarks.update(connection.take_bookmark());
+        }
+        this.connection = Some(connection);
+        Poll::Ready(match result {
+            Ok(Some(values)) => Some(Ok(Record::new(this.keys.clone(), values))),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt;
+
+    use crate::bolt::message::MessageValue;
+    use crate::bolt::messages::{self, Dictionary, Pull};
+    use crate::bolt::stub::{fields, record, stub_server, success, tags};
+    use crate::summary::QueryType;
+    use crate::{Config, Driver};
+
+    fn int(n: i8) -> MessageValue {
+        MessageValue::TinyInt(n)
+    }
+
+    fn has_more() -> (&'static str, MessageValue) {
+        ("has_more", MessageValue::Bool(true))
+    }
+
+    #[tokio::test]
+    async fn pulls_records_in_batches() {
+        let (address, server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&["n"]))])],
+            vec![
+                record(vec![int(1)]),
+                record(vec![int(2)]),
+                success(vec![has_more()]),
+            ],
+            vec![record(vec![int(3)]), success(vec![])],
+        ]])
+        .await;
+        let mut config = Config::from_uri(&address).unwrap();
+        config.fetch_size = 2;
+        let driver = Driver::with_config(config);
+        let mut session = driver.session().await.unwrap();
+        let result = session
+            .run("UNWIND range(1, 3) AS n RETURN n", Dictionary::new())
+            .await;
+        let values: Vec<MessageValue> = result
+            .unwrap()
+            .map_ok(|record| record.values()[0].clone())
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(values, [int(1), int(2), int(3)]);
+
+        let requests = server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [
+                messages::HELLO,
+                messages::RUN,
+                messages::PULL,
+                messages::PULL
+            ]
+        );
+        assert_eq!(Pull::try_from(requests[2].clone()).unwrap(), Pull::new(2));
+    }
+
+    #[tokio::test]
+    async fn dropped_streams_are_discarded() {
+        let (address, server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![("fields", fields(&["n"]))])],
+            vec![
+                record(vec![int(1)]),
+                record(vec![int(2)]),
+                success(vec![has_more()]),
+            ],
+            vec![success(vec![])],
+            vec![success(vec![])],
+        ]])
+        .await;
+        let mut config = Config::from_uri(&address).unwrap();
+        config.fetch_size = 2;
+        let driver = Driver::with_config(config);
+        let mut session = driver.session().await.unwrap();
+        let mut result = session
+            .run("UNWIND range(1, 3) AS n RETURN n", Dictionary::new())
+            .await
+            .unwrap();
+        assert_eq!(result.next().await.unwrap().unwrap().values(), [int(1)]);
+        drop(result);
+        session.begin_transaction().await.unwrap();
+
+        let requests = server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [
+                messages::HELLO,
+                messages::RUN,
+                messages::PULL,
+                messages::DISCARD,
+                messages::BEGIN,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn consuming_returns_the_summary() {
+        let stats = Dictionary::from([("nodes-created".to_string(), int(1))]);
+        let (address, server) = stub_server(vec![vec![
+            vec![success(vec![])],
+            vec![success(vec![
+                ("fields", fields(&["n"])),
+                ("t_first", int(2)),
+            ])],
+            vec![
+                record(vec![int(1)]),
+                success(vec![
+                    ("type", MessageValue::String("rw".to_string())),
+                    ("t_last", int(4)),
+                    ("stats", MessageValue::Map(stats)),
+                ]),
+            ],
+        ]])
+        .await;
+        let driver = Driver::new(&address, crate::Auth::None).unwrap();
+        let mut session = driver.session().await.unwrap();
+        let mut result = session
+            .run("CREATE (n) RETURN 1 AS n", Dictionary::new())
+            .await
+            .unwrap();
+        assert!(result.next().await.unwrap().is_ok());
+        assert!(result.next().await.is_none());
+        let summary = result.consume().await.unwrap();
+        assert_eq!(summary.query_type, Some(QueryType::ReadWrite));
+        assert_eq!(summary.counters.nodes_created, 1);
+        assert_eq!(
+            summary.result_available_after,
+            Some(std::time::Duration::from_millis(2))
+        );
+
+        let requests = server.await.unwrap().remove(0);
+        assert_eq!(
+            tags(&requests),
+            [messages::HELLO, messages::RUN, messages::PULL]
+        );
+    }
+}
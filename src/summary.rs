@@ -0,0 +1,317 @@
+use std::time::Duration;
+
+use crate::bolt::message::MessageValue;
+use crate::bolt::messages::Dictionary;
+
+// What the server reported about a query once its results were consumed. The
+// metadata is read leniently: a summary is informational, and by the time it
+// arrives the query has already run, so entries of unexpected types are
+// skipped rather than reported as errors.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResultSummary {
+    pub query_type: Option<QueryType>,
+    pub counters: Counters,
+    // Until the first record was available, and until the last one was
+    // consumed, as measured by the server.
+    pub result_available_after: Option<Duration>,
+    pub result_consumed_after: Option<Duration>,
+    // The plan of an EXPLAIN, or the profiled plan of a PROFILE.
+    pub plan: Option<Plan>,
+    pub notifications: Vec<Notification>,
+    // The database the query ran against, from Bolt 4.0.
+    pub database: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryType {
+    ReadOnly,
+    ReadWrite,
+    WriteOnly,
+    SchemaWrite,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub nodes_created: i64,
+    pub nodes_deleted: i64,
+    pub relationships_created: i64,
+    pub relationships_deleted: i64,
+    pub properties_set: i64,
+    pub labels_added: i64,
+    pub labels_removed: i64,
+    pub indexes_added: i64,
+    pub indexes_removed: i64,
+    pub constraints_added: i64,
+    pub constraints_removed: i64,
+    pub system_updates: i64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Plan {
+    pub operator_type: String,
+    pub arguments: Dictionary,
+    pub identifiers: Vec<String>,
+    pub children: Vec<Plan>,
+    // Only for PROFILE.
+    pub statistics: Option<ProfileStatistics>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProfileStatistics {
+    pub db_hits: i64,
+    pub rows: i64,
+    pub page_cache_hits: i64,
+    pub page_cache_misses: i64,
+    // Time spent in the operator, when the server measured it.
+    pub time: Option<Duration>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub code: String,
+    pub title: String,
+    pub description: String,
+    // WARNING or INFORMATION.
+    pub severity: String,
+    pub category: Option<String>,
+    pub position: Option<InputPosition>,
+}
+
+// Where in the query a notification points; offsets count from 0, lines and
+// columns from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputPosition {
+    pub offset: i64,
+    pub line: i64,
+    pub column: i64,
+}
+
+impl Counters {
+    pub fn contains_updates(&self) -> bool {
+        self.nodes_created > 0
+            || self.nodes_deleted > 0
+            || self.relationships_created > 0
+            || self.relationships_deleted > 0
+            || self.properties_set > 0
+            || self.labels_added > 0
+            || self.labels_removed > 0
+            || self.indexes_added > 0
+            || self.indexes_removed > 0
+            || self.constraints_added > 0
+            || self.constraints_removed > 0
+    }
+
+    pub fn contains_system_updates(&self) -> bool {
+        self.system_updates > 0
+    }
+
+    fn parse(stats: &Dictionary) -> Counters {
+        let count = |key: &str| int(stats, key).unwrap_or(0);
+        Counters {
+            nodes_created: count("nodes-created"),
+            nodes_deleted: count("nodes-deleted"),
+            relationships_created: count("relationships-created"),
+            relationships_deleted: count("relationships-deleted"),
+            properties_set: count("properties-set"),
+            labels_added: count("labels-added"),
+            labels_removed: count("labels-removed"),
+            indexes_added: count("indexes-added"),
+            indexes_removed: count("indexes-removed"),
+            constraints_added: count("constraints-added"),
+            constraints_removed: count("constraints-removed"),
+            system_updates: count("system-updates"),
+        }
+    }
+}
+
+fn int(map: &Dictionary, key: &str) -> Option<i64> {
+    map.get(key)?.as_i64().ok()
+}
+
+fn string(map: &Dictionary, key: &str) -> Option<String> {
+    match map.get(key)? {
+        MessageValue::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn map<'a>(map: &'a Dictionary, key: &str) -> Option<&'a Dictionary> {
+    match map.get(key)? {
+        MessageValue::Map(map) => Some(map),
+        _ => None,
+    }
+}
+
+fn maps<'a>(map: &'a Dictionary, key: &str) -> impl Iterator<Item = &'a Dictionary> {
+    let list = match map.get(key) {
+        Some(MessageValue::List(list)) => list.as_slice(),
+        _ => &[],
+    };
+    list.iter().filter_map(|item| match item {
+        MessageValue::Map(map) => Some(map),
+        _ => None,
+    })
+}
+
+fn millis(map: &Dictionary, key: &str) -> Option<Duration> {
+    let millis = u64::try_from(int(map, key)?).ok()?;
+    Some(Duration::from_millis(millis))
+}
+
+impl Plan {
+    fn parse(plan: &Dictionary) -> Plan {
+        let identifiers = match plan.get("identifiers") {
+            Some(MessageValue::List(list)) => list
+                .iter()
+                .filter_map(|identifier| match identifier {
+                    MessageValue::String(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let statistics = plan.contains_key("dbHits").then(|| ProfileStatistics {
+            db_hits: int(plan, "dbHits").unwrap_or(0),
+            rows: int(plan, "rows").unwrap_or(0),
+            page_cache_hits: int(plan, "pageCacheHits").unwrap_or(0),
+            page_cache_misses: int(plan, "pageCacheMisses").unwrap_or(0),
+            // Reported in nanoseconds, unlike the summary's timings.
+            time: int(plan, "time")
+                .and_then(|nanos| u64::try_from(nanos).ok())
+                .map(Duration::from_nanos),
+        });
+        Plan {
+            operator_type: string(plan, "operatorType").unwrap_or_default(),
+            arguments: map(plan, "args").cloned().unwrap_or_default(),
+            identifiers,
+            children: maps(plan, "children").map(Plan::parse).collect(),
+            statistics,
+        }
+    }
+}
+
+impl Notification {
+    fn parse(notification: &Dictionary) -> Notification {
+        let position = map(notification, "position").map(|position| InputPosition {
+            offset: int(position, "offset").unwrap_or(0),
+            line: int(position, "line").unwrap_or(0),
+            column: int(position, "column").unwrap_or(0),
+        });
+        Notification {
+            code: string(notification, "code").unwrap_or_default(),
+            title: string(notification, "title").unwrap_or_default(),
+            description: string(notification, "description").unwrap_or_default(),
+            severity: string(notification, "severity").unwrap_or_default(),
+            category: string(notification, "category"),
+            position,
+        }
+    }
+}
+
+impl ResultSummary {
+    // From the SUCCESS metadata of the RUN and of the PULL or DISCARD that
+    // ended the stream.
+    pub(crate) fn new(run: &Dictionary, end: &Dictionary) -> ResultSummary {
+        let query_type = match string(end, "type").as_deref() {
+            Some("r") => Some(QueryType::ReadOnly),
+            Some("rw") => Some(QueryType::ReadWrite),
+            Some("w") => Some(QueryType::WriteOnly),
+            Some("s") => Some(QueryType::SchemaWrite),
+            _ => None,
+        };
+        let plan = map(end, "profile").or_else(|| map(end, "plan"));
+        ResultSummary {
+            query_type,
+            counters: map(end, "stats").map(Counters::parse).unwrap_or_default(),
+            result_available_after: millis(run, "t_first"),
+            result_consumed_after: millis(end, "t_last"),
+            plan: plan.map(Plan::parse),
+            notifications: maps(end, "notifications")
+                .map(Notification::parse)
+                .collect(),
+            database: string(end, "db"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &str, value: impl Into<MessageValue>) -> (String, MessageValue) {
+        (key.to_string(), value.into())
+    }
+
+    #[test]
+    fn parses_summary_metadata() {
+        let run = Dictionary::from([entry("t_first", 3)]);
+        let stats = Dictionary::from([entry("nodes-created", 2), entry("properties-set", 4)]);
+        let position = Dictionary::from([entry("offset", 7), entry("line", 1), entry("column", 8)]);
+        let notification = Dictionary::from([
+            entry("code", "Neo.ClientNotification.Statement.CartesianProduct"),
+            entry("title", "Cartesian product"),
+            entry("severity", "WARNING"),
+            entry("position", MessageValue::Map(position)),
+        ]);
+        let end = Dictionary::from([
+            entry("type", "rw"),
+            entry("t_last", 5),
+            entry("db", "neo4j"),
+            entry("stats", MessageValue::Map(stats)),
+            entry("notifications", vec![MessageValue::Map(notification)]),
+        ]);
+        let summary = ResultSummary::new(&run, &end);
+        assert_eq!(summary.query_type, Some(QueryType::ReadWrite));
+        assert_eq!(summary.counters.nodes_created, 2);
+        assert_eq!(summary.counters.properties_set, 4);
+        assert!(summary.counters.contains_updates());
+        assert!(!summary.counters.contains_system_updates());
+        assert_eq!(
+            summary.result_available_after,
+            Some(Duration::from_millis(3))
+        );
+        assert_eq!(
+            summary.result_consumed_after,
+            Some(Duration::from_millis(5))
+        );
+        assert_eq!(summary.database.as_deref(), Some("neo4j"));
+        let notification = &summary.notifications[0];
+        assert_eq!(notification.severity, "WARNING");
+        assert_eq!(notification.position.unwrap().column, 8);
+        assert_eq!(summary.plan, None);
+
+        let empty = ResultSummary::new(&Dictionary::new(), &Dictionary::new());
+        assert_eq!(empty, ResultSummary::default());
+    }
+
+    #[test]
+    fn parses_profiled_plans() {
+        let scan = Dictionary::from([
+            entry("operatorType", "AllNodesScan"),
+            entry("identifiers", vec!["n"]),
+            entry("dbHits", 11),
+            entry("rows", 10),
+        ]);
+        let result = Dictionary::from([
+            entry("operatorType", "ProduceResults"),
+            entry(
+                "args",
+                MessageValue::Map(Dictionary::from([entry("planner", "COST")])),
+            ),
+            entry("children", vec![MessageValue::Map(scan)]),
+            entry("dbHits", 0),
+            entry("rows", 10),
+            entry("time", 2_000_000),
+        ]);
+        let end = Dictionary::from([entry("profile", MessageValue::Map(result))]);
+        let plan = ResultSummary::new(&Dictionary::new(), &end).plan.unwrap();
+        assert_eq!(plan.operator_type, "ProduceResults");
+        assert_eq!(plan.arguments["planner"], "COST".into());
+        let statistics = plan.statistics.unwrap();
+        assert_eq!(statistics.time, Some(Duration::from_millis(2)));
+        let scan = &plan.children[0];
+        assert_eq!(scan.identifiers, ["n"]);
+        assert_eq!(scan.statistics.as_ref().unwrap().db_hits, 11);
+        assert!(scan.children.is_empty());
+    }
+}